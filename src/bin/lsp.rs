@@ -0,0 +1,8 @@
+fn main() {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(error) = lib::lsp::run(stdin.lock(), stdout.lock()) {
+        eprintln!("expert_system_lsp: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -1,14 +1,130 @@
+type Subcommand = fn(&str, &lib::options::Options) -> Result<bool, std::io::Error>;
+
+fn load_option(options: &mut lib::options::Options, arg: &str, usage: fn()) {
+    if let Err(error) = options.load(arg) {
+        eprintln!("Error: {:?}", error.to_string());
+        usage();
+        std::process::exit(1);
+    }
+}
+
+fn run_subcommand(name: &str, subcommand: Subcommand, args: &[String], usage: fn()) {
+    let mut options = lib::options::Options::new();
+    let mut success = true;
+    let mut launched = 0;
+
+    for arg in args.iter() {
+        match arg.chars().next() {
+            Some('-') => load_option(&mut options, arg, usage),
+            _ => {
+                match subcommand(arg, &options) {
+                    Ok(passed) => success &= passed,
+                    Err(error) => {
+                        eprintln!("{}: {}: {:?}", name, arg, error.to_string());
+                        success = false;
+                    }
+                }
+                launched += 1;
+            }
+        }
+    }
+    if launched == 0 {
+        usage();
+    }
+    if !success {
+        std::process::exit(1);
+    }
+}
+
+fn usage() {
+    println!("usage: ./expert_system [-giflcdoe] [input_file ...]");
+    println!("       ./expert_system test test_dir ...");
+    println!("       ./expert_system check input_file ...");
+    println!("       ./expert_system fmt [--check] input_file ...");
+    println!("       ./expert_system --watch [-giflcdoe] input_file");
+    println!("       ./expert_system serve [--port=port] input_file ...");
+    println!("       -g : print ascii graph");
+    println!("       -i : interactive fact validation");
+    println!("       -f : print file");
+    println!("       -l : print result into log file");
+    println!("       -c : print only comments (only if -f inactive)");
+    println!("       -d : export rules and proof trees as graphviz dot files");
+    println!("       --dot=dir : -d writing the dot files in dir instead of the current one");
+    println!("       -o : open world, facts neither asserted nor derived are undetermined");
+    println!("       -e : explain the results in sentences (-ee, -eee: more details)");
+    println!("       --template=file : sentences used by -e, as 'key = value' lines");
+    println!("       --certainty : certainty factors, rules and initial facts weighted");
+    println!("                     as 'A + B => C [0.8]' and '=A [0.9]'");
+    println!("       --fuzzy[=norm] : truth degrees in [0, 1], as '=A [0.7]', AND and OR");
+    println!("                        being min/max, product or lukasiewicz norms");
+    println!("       --probability : priors on initial facts, as '=A [0.7]', and the");
+    println!("                       probability of each query, counted exactly over");
+    println!("                       the worlds of the uncertain facts");
+    println!("       --samples=n : --probability estimated over n sampled worlds");
+    println!("       --watch : solve again on every change of input_file or its includes,");
+    println!("                 printing a diff against the previous run\n");
+    println!("       test: run every input of test_dir against its .refout (expected output)");
+    println!("             or .referr (expected error) file, or its inline expectations");
+    println!("       check: verify the '?= X true' expectations written in input_file");
+    println!("       fmt: rewrite input_file in canonical form, --check only reports");
+    println!("            the files that would change");
+    println!("       serve: answer JSON requests on localhost (default port 8080):");
+    println!("              GET /kb, GET|POST /kb/name/facts, GET /kb/name/rules,");
+    println!("              POST /kb/name/query, POST /kb/name/explain,");
+    println!("              POST /kb/name/assert and POST /kb/name/retract");
+    println!("       expert_system_lsp: language server for rule files over stdio\n");
+    println!("       cargo run [-- -giflcdo] input_file ...");
+    println!("       note: '--' allow cargo to load options");
+}
+
+// Options apply to the single watched input wherever they are written.
+fn watch(args: &[String]) {
+    let mut options = lib::options::Options::new();
+    let mut inputs = Vec::new();
+    for arg in args.iter() {
+        match arg.chars().next() {
+            Some('-') => load_option(&mut options, arg, usage),
+            _ => inputs.push(arg),
+        }
+    }
+    match inputs.as_slice() {
+        [input] => lib::watch::run(input, &options),
+        _ => {
+            eprintln!("Error: \"Options: --watch takes a single input file\"");
+            usage();
+            std::process::exit(1);
+        }
+    }
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let nb_args = args.len();
     let mut options = lib::options::Options::new();
     let mut launched = 0;
 
+    if nb_args > 1 && args[1] == "test" {
+        return run_subcommand("test", lib::regression::run, &args[2..], || {
+            println!("usage: ./expert_system test test_dir ...")
+        });
+    } else if nb_args > 1 && args[1] == "check" {
+        return run_subcommand("check", lib::regression::check, &args[2..], || {
+            println!("usage: ./expert_system check input_file ...")
+        });
+    } else if nb_args > 1 && args[1] == "fmt" {
+        return run_subcommand("fmt", lib::format::run, &args[2..], || {
+            println!("usage: ./expert_system fmt [--check] input_file ...")
+        });
+    } else if nb_args > 1 && args[1] == "serve" {
+        return lib::serve::run(&args[2..]);
+    } else if args.iter().any(|arg| arg == "--watch") {
+        return watch(&args[1..]);
+    }
     let mut i = 1;
     while i < nb_args {
         let arg = &args[i];
         match arg.chars().next() {
-            Some('-') => options.load(arg),
+            Some('-') => load_option(&mut options, arg, usage),
             _ => {
                 lib::expert_system::run(arg, &options);
                 launched += 1;
@@ -17,13 +133,6 @@ fn main() {
         i += 1;
     }
     if launched == 0 {
-        println!("usage: ./expert_system [-giflc] [input_file ...]");
-        println!("       -g : print ascii graph");
-        println!("       -i : interactive fact validation");
-        println!("       -f : print file");
-        println!("       -l : print result into log file");
-        println!("       -c : print only comments (only if -f inactive)\n");
-        println!("       cargo run [-- -giflc] input_file ...");
-        println!("       note: '--' allow cargo to load options");
+        usage();
     }
 }
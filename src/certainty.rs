@@ -0,0 +1,98 @@
+use crate::facts::{Fact, Measure, State};
+use crate::options::Options;
+use crate::rules::rule::token::Operand;
+use crate::weighted::{self, Algebra};
+
+// A rule fires when the certainty of its premise is above it, as in MYCIN.
+pub const THRESHOLD: f64 = 0.2;
+
+// MYCIN combination of two certainty factors concluding a same fact.
+pub fn combine(x: f64, y: f64) -> f64 {
+    if x >= 0.0 && y >= 0.0 {
+        x + y * (1.0 - x)
+    } else if x < 0.0 && y < 0.0 {
+        x + y * (1.0 + x)
+    } else {
+        let divisor = 1.0 - x.abs().min(y.abs());
+        if divisor == 0.0 {
+            0.0
+        } else {
+            (x + y) / divisor
+        }
+    }
+}
+
+// Certainty algebra: AND is the minimum, OR the maximum, NOT the opposite,
+// the other operators being written with those three.
+pub fn compute(operand: Operand, lhs: f64, rhs: f64) -> f64 {
+    match operand {
+        Operand::Not => -lhs,
+        Operand::And => lhs.min(rhs),
+        Operand::Or => lhs.max(rhs),
+        Operand::Xor => compute(
+            Operand::Or,
+            compute(Operand::And, lhs, -rhs),
+            compute(Operand::And, -lhs, rhs),
+        ),
+        Operand::Nand => -compute(Operand::And, lhs, rhs),
+        Operand::Nor => -compute(Operand::Or, lhs, rhs),
+        Operand::Xnor => -compute(Operand::Xor, lhs, rhs),
+        Operand::Imply => compute(Operand::Or, -lhs, rhs),
+        Operand::Equiv => compute(Operand::Xnor, lhs, rhs),
+        _ => 0.0,
+    }
+}
+
+// MYCIN certainty factors, from -1 (surely false) to 1 (surely true).
+pub struct Mycin;
+
+impl Algebra for Mycin {
+    fn name(&self) -> &'static str {
+        "CF"
+    }
+
+    fn measure(&self, value: f64) -> Measure {
+        Measure::Certainty(value)
+    }
+
+    // Weak factors are undetermined in an open world and false otherwise.
+    fn state(&self, value: f64, options: &Options) -> State {
+        weighted::state(value, -THRESHOLD, THRESHOLD, options)
+    }
+
+    fn initial(&self, fact: &Fact) -> f64 {
+        let sign = self.truth(fact.state.get());
+        fact.certainty.get().unwrap_or(sign)
+    }
+
+    fn truth(&self, value: bool) -> f64 {
+        if value {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    fn not(&self, value: f64) -> f64 {
+        -value
+    }
+
+    fn compute(&self, operand: Operand, lhs: f64, rhs: f64) -> f64 {
+        compute(operand, lhs, rhs)
+    }
+
+    fn fire(&self, premise: f64, strength: f64) -> Option<f64> {
+        match premise > THRESHOLD {
+            true => Some(premise * strength),
+            false => None,
+        }
+    }
+
+    fn combine(&self, x: f64, y: f64) -> f64 {
+        combine(x, y)
+    }
+
+    fn conclude(&self, positive: f64, negative: f64) -> f64 {
+        combine(positive, -negative)
+    }
+}
@@ -1,6 +1,7 @@
 use crate::facts::{Fact, Facts};
 use crate::graph::{Graph, NodeIndex};
-use crate::rules::rule::{token::Token, Side};
+use crate::options::Options;
+use crate::rules::rule::token::{Operand, Token};
 
 use std::io::{Error, ErrorKind};
 
@@ -22,34 +23,6 @@ pub fn solved_queries(facts: &Facts) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn impliance(side: &mut Side, c: char) -> Result<(), Error> {
-    if *side == Side::Rhs {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Rules: <, =, or > oversupplied",
-        ));
-    }
-    let side_cpy: Side = *side;
-    match c {
-        '=' => *side = Side::Pending,
-        '<' => *side = Side::Bidirectional,
-        '>' => *side = Side::Rhs,
-        _ => {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Rules: impliance wrong format",
-            ))
-        }
-    };
-    if side_cpy == *side {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Rules: impliance wrong format",
-        ));
-    }
-    Ok(())
-}
-
 pub fn rule_composition(tokens: &[Token], line: &str) -> Result<(), Error> {
     let mut last = &Token::new(None, None);
     if tokens.is_empty() {
@@ -66,7 +39,7 @@ pub fn rule_composition(tokens: &[Token], line: &str) -> Result<(), Error> {
             ));
         }
         if !last.is_empty() {
-            if token.is_fact() && last.is_fact() {
+            if token.is_value() && last.is_value() {
                 return Err(Error::new(
                     ErrorKind::InvalidData,
                     format!("Rules: contiguous facts (at {})", line),
@@ -97,10 +70,10 @@ pub fn infinite_rule_loop<'a>(
                 cur = node.parent.unwrap();
                 node = graph.get(cur).unwrap();
                 if let Some(fact) = node.content.fact {
-                    if fact.letter == ref_fact.letter {
+                    if fact.name == ref_fact.name {
                         return Err(Error::new(
                             ErrorKind::InvalidInput,
-                            "Tree builder (inf checker): infinite loop",
+                            format!("Tree builder (inf checker): infinite loop ({})", fact.name),
                         ));
                     }
                 }
@@ -113,3 +86,57 @@ pub fn infinite_rule_loop<'a>(
         )),
     }
 }
+
+// Certainty factors are MYCIN ones, from -1 (surely false) to 1 (surely true),
+// with --fuzzy they are truth degrees and with --probability priors, from 0 to 1.
+pub fn certainty(text: &str, options: &Options) -> Result<f64, Error> {
+    match text.trim().parse::<f64>() {
+        Ok(prior) if options.probability && !(0.0..=1.0).contains(&prior) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Probability: prior out of [0, 1] (at [{}])", text),
+        )),
+        Ok(degree) if options.fuzzy.is_some() && !(0.0..=1.0).contains(&degree) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Fuzzy: degree out of [0, 1] (at [{}])", text),
+        )),
+        Ok(factor) if (-1.0..=1.0).contains(&factor) => Ok(factor),
+        Ok(_) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Certainty: factor out of [-1, 1] (at [{}])", text),
+        )),
+        Err(_) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Certainty: invalid factor (at [{}])", text),
+        )),
+    }
+}
+
+// A conclusion must tell which value each of its facts takes, which
+// implication, equivalence, NAND, NOR, XNOR and cardinalities don't.
+pub fn conclusion(tokens: &[Token], line: &str) -> Result<(), Error> {
+    for token in tokens {
+        if token.constant.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Rules: constant in conclusion (at {})", line),
+            ));
+        }
+        if let Some(
+            Operand::Imply
+            | Operand::Equiv
+            | Operand::Nand
+            | Operand::Nor
+            | Operand::Xnor
+            | Operand::AtLeast(_)
+            | Operand::AtMost(_)
+            | Operand::Exactly(_),
+        ) = token.operand
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Rules: ambiguous conclusion (at {})", line),
+            ));
+        }
+    }
+    Ok(())
+}
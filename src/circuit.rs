@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+pub const FALSE: usize = 0;
+pub const TRUE: usize = 1;
+// Exact counting gives up past this many steps, each the visit of a node.
+pub const STEPS: usize = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Node {
+    Constant(bool),
+    Variable(usize),
+    Not(usize),
+    And(usize, usize),
+    Or(usize, usize),
+}
+
+// Formulas over independent random variables, each subformula being built
+// once and known by its index so that the counts are cached by it.
+pub struct Circuit {
+    nodes: Vec<Node>,
+    index: HashMap<Node, usize>,
+    // Sorted variables of each node.
+    variables: Vec<Rc<Vec<usize>>>,
+    // Probability of each variable being true.
+    priors: Vec<f64>,
+    counts: HashMap<usize, f64>,
+    pub steps: usize,
+}
+
+impl Circuit {
+    pub fn new(priors: Vec<f64>) -> Self {
+        let mut circuit = Circuit {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            variables: Vec::new(),
+            priors,
+            counts: HashMap::new(),
+            steps: 0,
+        };
+        circuit.add(Node::Constant(false));
+        circuit.add(Node::Constant(true));
+        circuit
+    }
+
+    fn add(&mut self, node: Node) -> usize {
+        if let Some(index) = self.index.get(&node) {
+            return *index;
+        }
+        let variables = match node {
+            Node::Constant(_) => Rc::new(Vec::new()),
+            Node::Variable(variable) => Rc::new(vec![variable]),
+            Node::Not(a) => self.variables[a].clone(),
+            Node::And(a, b) | Node::Or(a, b) => {
+                let mut merged: Vec<usize> = self.variables[a]
+                    .iter()
+                    .chain(self.variables[b].iter())
+                    .copied()
+                    .collect();
+                merged.sort_unstable();
+                merged.dedup();
+                Rc::new(merged)
+            }
+        };
+        self.nodes.push(node);
+        self.variables.push(variables);
+        self.index.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn constant(value: bool) -> usize {
+        if value {
+            TRUE
+        } else {
+            FALSE
+        }
+    }
+
+    pub fn variable(&mut self, variable: usize) -> usize {
+        self.add(Node::Variable(variable))
+    }
+
+    pub fn not(&mut self, a: usize) -> usize {
+        match self.nodes[a] {
+            Node::Constant(value) => Circuit::constant(!value),
+            Node::Not(b) => b,
+            _ => self.add(Node::Not(a)),
+        }
+    }
+
+    fn complements(&self, a: usize, b: usize) -> bool {
+        self.nodes[a] == Node::Not(b) || self.nodes[b] == Node::Not(a)
+    }
+
+    pub fn and(&mut self, a: usize, b: usize) -> usize {
+        match (a, b) {
+            (FALSE, _) | (_, FALSE) => FALSE,
+            (TRUE, other) | (other, TRUE) => other,
+            _ if a == b => a,
+            _ if self.complements(a, b) => FALSE,
+            _ => self.add(Node::And(a.min(b), a.max(b))),
+        }
+    }
+
+    pub fn or(&mut self, a: usize, b: usize) -> usize {
+        match (a, b) {
+            (TRUE, _) | (_, TRUE) => TRUE,
+            (FALSE, other) | (other, FALSE) => other,
+            _ if a == b => a,
+            _ if self.complements(a, b) => TRUE,
+            _ => self.add(Node::Or(a.min(b), a.max(b))),
+        }
+    }
+
+    // "if a then b else c".
+    pub fn ite(&mut self, a: usize, b: usize, c: usize) -> usize {
+        let then = self.and(a, b);
+        let not = self.not(a);
+        let otherwise = self.and(not, c);
+        self.or(then, otherwise)
+    }
+
+    // At least k of the formulas are true, counted one formula after the
+    // other.
+    pub fn at_least(&mut self, k: usize, formulas: &[usize]) -> usize {
+        let mut at_least = vec![FALSE; k + 1];
+        at_least[0] = TRUE;
+        for formula in formulas.iter() {
+            for j in (1..=k).rev() {
+                let one_more = self.and(at_least[j - 1], *formula);
+                at_least[j] = self.or(at_least[j], one_more);
+            }
+        }
+        at_least[k]
+    }
+
+    // The formula with a variable set to a value.
+    fn restrict(
+        &mut self,
+        a: usize,
+        variable: usize,
+        value: bool,
+        done: &mut HashMap<usize, usize>,
+    ) -> usize {
+        if self.variables[a].binary_search(&variable).is_err() {
+            return a;
+        }
+        if let Some(restricted) = done.get(&a) {
+            return *restricted;
+        }
+        self.steps += 1;
+        let restricted = match self.nodes[a] {
+            Node::Constant(_) => a,
+            Node::Variable(_) => Circuit::constant(value),
+            Node::Not(b) => {
+                let b = self.restrict(b, variable, value, done);
+                self.not(b)
+            }
+            Node::And(b, c) => {
+                let b = self.restrict(b, variable, value, done);
+                let c = self.restrict(c, variable, value, done);
+                self.and(b, c)
+            }
+            Node::Or(b, c) => {
+                let b = self.restrict(b, variable, value, done);
+                let c = self.restrict(c, variable, value, done);
+                self.or(b, c)
+            }
+        };
+        done.insert(a, restricted);
+        restricted
+    }
+
+    // Operands of nested ANDs, or of nested ORs, each node being met once.
+    fn operands(
+        &mut self,
+        a: usize,
+        conjunction: bool,
+        seen: &mut HashSet<usize>,
+        operands: &mut Vec<usize>,
+    ) {
+        if !seen.insert(a) {
+            return;
+        }
+        self.steps += 1;
+        match self.nodes[a] {
+            Node::And(b, c) if conjunction => {
+                self.operands(b, conjunction, seen, operands);
+                self.operands(c, conjunction, seen, operands);
+            }
+            Node::Or(b, c) if !conjunction => {
+                self.operands(b, conjunction, seen, operands);
+                self.operands(c, conjunction, seen, operands);
+            }
+            _ => operands.push(a),
+        }
+    }
+
+    // Operands grouped so that no two groups share a variable.
+    fn components(&mut self, operands: &[usize]) -> Vec<Vec<usize>> {
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        let mut parents: Vec<usize> = (0..operands.len()).collect();
+        let mut owners: HashMap<usize, usize> = HashMap::new();
+        for (i, operand) in operands.iter().enumerate() {
+            for variable in self.variables[*operand].iter() {
+                self.steps += 1;
+                let owner = *owners.entry(*variable).or_insert(i);
+                let (a, b) = (root(&mut parents, owner), root(&mut parents, i));
+                parents[a] = b;
+            }
+        }
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, operand) in operands.iter().enumerate() {
+            groups
+                .entry(root(&mut parents, i))
+                .or_default()
+                .push(*operand);
+        }
+        let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+        groups.sort();
+        groups
+    }
+
+    // Probability of an AND, or an OR, of operands: the product over its
+    // independent components, a component of several operands being
+    // expanded on the variable most of them share.
+    fn decompose(&mut self, a: usize, conjunction: bool) -> Option<f64> {
+        let mut operands = Vec::new();
+        self.operands(a, conjunction, &mut HashSet::new(), &mut operands);
+        let components = self.components(&operands);
+        if components.len() == 1 {
+            let mut shared: HashMap<usize, usize> = HashMap::new();
+            for operand in operands.iter() {
+                for variable in self.variables[*operand].iter() {
+                    *shared.entry(*variable).or_insert(0) += 1;
+                }
+            }
+            let variable = shared
+                .iter()
+                .max_by_key(|(variable, count)| (**count, usize::MAX - **variable))
+                .map(|(variable, _)| *variable)?;
+            return self.expand(a, variable);
+        }
+        let mut product = 1.0;
+        for component in components.iter() {
+            let mut b = Circuit::constant(conjunction);
+            for operand in component.iter() {
+                b = match conjunction {
+                    true => self.and(b, *operand),
+                    false => self.or(b, *operand),
+                };
+            }
+            let probability = self.count(b)?;
+            product *= if conjunction {
+                probability
+            } else {
+                1.0 - probability
+            };
+        }
+        Some(if conjunction { product } else { 1.0 - product })
+    }
+
+    // Shannon expansion: the weighted sum of the formula with the variable
+    // true and false.
+    fn expand(&mut self, a: usize, variable: usize) -> Option<f64> {
+        if self.steps > STEPS {
+            return None;
+        }
+        let prior = self.priors[variable];
+        let high = self.restrict(a, variable, true, &mut HashMap::new());
+        let low = self.restrict(a, variable, false, &mut HashMap::new());
+        Some(prior * self.count(high)? + (1.0 - prior) * self.count(low)?)
+    }
+
+    fn count(&mut self, a: usize) -> Option<f64> {
+        if let Some(probability) = self.counts.get(&a) {
+            return Some(*probability);
+        }
+        let probability = match self.nodes[a] {
+            Node::Constant(true) => 1.0,
+            Node::Constant(false) => 0.0,
+            Node::Variable(variable) => self.priors[variable],
+            Node::Not(b) => 1.0 - self.count(b)?,
+            Node::And(_, _) => self.decompose(a, true)?,
+            Node::Or(_, _) => self.decompose(a, false)?,
+        };
+        self.counts.insert(a, probability);
+        Some(probability)
+    }
+
+    // Probability of a formula, the weighted count of its models, None once
+    // the circuit took more than STEPS steps.
+    pub fn probability(&mut self, a: usize) -> Option<f64> {
+        self.count(a)
+    }
+}
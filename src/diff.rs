@@ -0,0 +1,102 @@
+const CONTEXT: usize = 3;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<(Edit, usize, usize)> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut script = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            script.push((Edit::Keep, i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            script.push((Edit::Remove, i, j));
+            i += 1;
+        } else {
+            script.push((Edit::Add, i, j));
+            j += 1;
+        }
+    }
+    script
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 1 {
+        format!("{}", start + 1)
+    } else if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+// Line based unified diff of two texts, empty when they are identical.
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let script = edit_script(&old_lines, &new_lines);
+    if script.iter().all(|(edit, _, _)| *edit == Edit::Keep) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut k = 0;
+    while k < script.len() {
+        if script[k].0 == Edit::Keep {
+            k += 1;
+            continue;
+        }
+        let start = k.saturating_sub(CONTEXT);
+        let mut end = k;
+        let mut keeps = 0;
+        while end < script.len() && keeps <= 2 * CONTEXT {
+            if script[end].0 == Edit::Keep {
+                keeps += 1;
+            } else {
+                keeps = 0;
+            }
+            end += 1;
+        }
+        end -= keeps.saturating_sub(CONTEXT).min(keeps);
+
+        let hunk = &script[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|(edit, _, _)| *edit != Edit::Add)
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|(edit, _, _)| *edit != Edit::Remove)
+            .count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(hunk[0].1, old_len),
+            hunk_range(hunk[0].2, new_len)
+        ));
+        for (edit, i, j) in hunk.iter() {
+            match edit {
+                Edit::Keep => out.push_str(&format!(" {}\n", old_lines[*i])),
+                Edit::Remove => out.push_str(&format!("-{}\n", old_lines[*i])),
+                Edit::Add => out.push_str(&format!("+{}\n", new_lines[*j])),
+            }
+        }
+        k = end;
+    }
+    out
+}
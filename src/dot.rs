@@ -0,0 +1,162 @@
+use crate::facts::Fact;
+use crate::graph::Graph;
+use crate::rules::{
+    rule::token::{Operand, Token},
+    Rules,
+};
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{prelude::*, Error};
+use std::path::{Path, PathBuf};
+
+fn fact_color(fact: &Fact) -> &'static str {
+    if !fact.determined.get() {
+        "lightgrey"
+    } else if fact.state.get() {
+        "palegreen"
+    } else {
+        "lightcoral"
+    }
+}
+
+fn fact_node(id: &str, fact: &Fact) -> String {
+    let label = match &fact.description {
+        Some(description) => format!("{}\\n{}", fact.name, escape(description)),
+        None => fact.name.clone(),
+    };
+    format!(
+        "    {} [label=\"{}\", shape=ellipse, style=filled, fillcolor={}];\n",
+        id,
+        label,
+        fact_color(fact)
+    )
+}
+
+// Qualified names hold dots, which DOT only accepts in quoted ids.
+fn fact_id(name: &str) -> String {
+    if name.contains('.') {
+        format!("\"fact_{}\"", name)
+    } else {
+        format!("fact_{}", name)
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Facts of a side with whether they are negated, rules being stored in
+// reversed RPN: an operator is followed by its right operand, then its left
+// one, and "!" by the subexpression it negates.
+fn side_facts<'a>(
+    tokens: &[Token<'a>],
+    i: &mut usize,
+    is_not: bool,
+    facts: &mut BTreeMap<String, &'a Fact>,
+    side: &mut Vec<(&'a Fact, bool)>,
+) {
+    let token = match tokens.get(*i) {
+        Some(token) => *token,
+        None => return,
+    };
+    *i += 1;
+    if let Some(fact) = token.fact {
+        facts.insert(fact.name.clone(), fact);
+        side.push((fact, is_not));
+        return;
+    }
+    match token.operand {
+        Some(Operand::Not) => side_facts(tokens, i, !is_not, facts, side),
+        Some(op) if op.is_unary() => side_facts(tokens, i, is_not, facts, side),
+        Some(_) => {
+            side_facts(tokens, i, is_not, facts, side);
+            side_facts(tokens, i, is_not, facts, side);
+        }
+        None => {}
+    }
+}
+
+fn side_edges<'a>(
+    tokens: &[Token<'a>],
+    facts: &mut BTreeMap<String, &'a Fact>,
+) -> Vec<(&'a Fact, bool)> {
+    let (mut side, mut i) = (Vec::new(), 0);
+    while i < tokens.len() {
+        side_facts(tokens, &mut i, false, facts, &mut side);
+    }
+    side
+}
+
+pub fn rules_to_dot<'a>(rules: &Rules<'a>) -> String {
+    let mut nodes = String::new();
+    let mut edges = String::new();
+    let mut facts: BTreeMap<String, &'a Fact> = BTreeMap::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+        nodes.push_str(&format!(
+            "    rule{} [label=\"{}\\n{}\", shape=box];\n",
+            i,
+            escape(&rule.provenance()),
+            escape(&rule.to_infix())
+        ));
+        for (fact, is_not) in side_edges(&rule.lhs, &mut facts) {
+            edges.push_str(&format!("    {} -> rule{}", fact_id(&fact.name), i));
+            edges.push_str(if is_not { " [style=dashed];\n" } else { ";\n" });
+        }
+        for (fact, is_not) in side_edges(&rule.rhs, &mut facts) {
+            edges.push_str(&format!("    rule{} -> {}", i, fact_id(&fact.name)));
+            edges.push_str(if is_not { " [style=dashed];\n" } else { ";\n" });
+        }
+    }
+    for (name, fact) in facts.iter() {
+        nodes.push_str(&fact_node(&fact_id(name), fact));
+    }
+    format!("digraph rules {{\n    rankdir=LR;\n{}{}}}\n", nodes, edges)
+}
+
+pub fn tree_to_dot(graph: &Graph<Token>, rules: &Rules) -> String {
+    let mut nodes = String::new();
+    let mut edges = String::new();
+
+    for (i, node) in graph.iter().enumerate() {
+        if let Some(fact) = node.content.fact {
+            nodes.push_str(&fact_node(&format!("node{}", i), fact));
+        } else if node.content.constant.is_some() {
+            nodes.push_str(&format!(
+                "    node{} [label=\"{}\", shape=box];\n",
+                i,
+                node.content.get_token_str()
+            ));
+        } else {
+            nodes.push_str(&format!(
+                "    node{} [label=\"{}\", shape=circle];\n",
+                i,
+                node.content.get_op_str()
+            ));
+        }
+        // The body under a fact is the rule which established it, if any.
+        let fired = node.content.fact.and_then(|fact| fact.rule.get());
+        for child in node.lhs.iter().chain(node.rhs.iter()) {
+            match fired.and_then(|index| rules.get(index)) {
+                Some(rule) => edges.push_str(&format!(
+                    "    node{} -> node{} [label=\"{}\"];\n",
+                    i,
+                    child,
+                    escape(&rule.provenance())
+                )),
+                None => edges.push_str(&format!("    node{} -> node{};\n", i, child)),
+            }
+        }
+    }
+    format!("digraph proof {{\n{}{}}}\n", nodes, edges)
+}
+
+// Writes the file in the directory, created if missing, and returns its path.
+pub fn to_file(dir: &str, name: &str, dot: &str) -> Result<PathBuf, Error> {
+    fs::create_dir_all(dir)?;
+    let path = Path::new(dir).join(name);
+    let mut f = File::create(&path)?;
+    f.write_all(dot.as_bytes())?;
+    Ok(path)
+}
@@ -1,15 +1,30 @@
-use crate::facts::{Fact, Facts};
+use crate::certainty::Mycin;
+use crate::dot;
+use crate::explain::{Explainer, Template};
+use crate::facts::{Fact, Facts, Measure, State};
+use crate::lexer;
 use crate::options::Options;
 use crate::print;
+use crate::probability::Probability;
 use crate::rules::Rules;
+use crate::scenario::{Scenario, ScenarioResult};
 use crate::solver;
+use crate::source::Source;
+use crate::weighted::{self, Algebra, Weighted};
 
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{prelude::*, BufReader, Error, ErrorKind};
+use std::io::{prelude::*, Error, ErrorKind};
 use std::path::Path;
 
-fn is_interactive(c: char, line: String, options: &Options) -> Result<String, Error> {
+fn is_interactive(
+    c: char,
+    line: String,
+    facts: &Facts,
+    options: &Options,
+) -> Result<String, Error> {
     if options.interactive && !options.file && !options.comment {
+        print!("{}", print::descriptions_to_string(&facts.fact_arr));
         print!("{}", c);
         std::io::stdout().flush()?;
         let mut buffer = String::new();
@@ -19,61 +34,152 @@ fn is_interactive(c: char, line: String, options: &Options) -> Result<String, Er
     Ok(line)
 }
 
-fn parser<'a>(file: File, facts: &'a Facts, options: &Options) -> Result<Rules<'a>, Error> {
-    let reader = BufReader::new(file);
-    let mut rules = Rules::new();
-    let mut has_initial_facts = false;
-    if options.file {
-        println!("=== FILE ===");
-    } else if options.comment {
-        println!("=== COMMENT ===");
+struct Parser<'a> {
+    facts: &'a Facts,
+    rules: Rules<'a>,
+    scenarios: Vec<Scenario<'a>>,
+    scenario: Scenario<'a>,
+    declared: HashSet<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(facts: &'a Facts) -> Self {
+        Parser {
+            facts,
+            rules: Rules::new(),
+            scenarios: Vec::new(),
+            scenario: Scenario::new(None),
+            declared: HashSet::new(),
+        }
     }
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            if options.file {
-                println!("{}", line);
-            }
-            match line.trim().chars().next() {
-                Some('A'..='Z') | Some('(') | Some('!') => {
-                    if options.interactive && !options.file && !options.comment {
-                        println!("{}", line);
-                    }
-                    rules.set_rule(&facts, &line, options)?;
+
+    fn parse_line(&mut self, line: &str, options: &Options) -> Result<(), Error> {
+        let facts = self.facts;
+        match line.trim().chars().next() {
+            Some(_) if lexer::declaration(line).is_some() => {
+                let fact = facts.declare(line)?;
+                if !self.declared.insert(fact.name.clone()) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Declarations: doublon (at {})", fact.name),
+                    ));
                 }
-                Some('=') => {
-                    let line = is_interactive('=', line.to_string(), options)?;
-                    facts.set_initial_facts(&line, options)?;
-                    has_initial_facts = true;
+            }
+            Some(c) if lexer::is_rule_start(c) => {
+                if options.interactive && !options.file && !options.comment {
+                    println!("{}", line);
                 }
-                Some('?') => {
-                    let line = is_interactive('?', line.to_string(), options)?;
-                    facts.set_queries(&line, options)?;
+                self.rules.set_rule(facts, line, options)?;
+            }
+            Some('=') => {
+                let line = is_interactive('=', line.to_string(), facts, options)?;
+                facts
+                    .set_initial_facts(&line, options)
+                    .map_err(|e| self.scenario.error(e))?;
+                self.scenario.has_initial_facts = true;
+            }
+            Some('?') if line.trim().starts_with("?=") => {
+                facts
+                    .set_expectations(line, options)
+                    .map_err(|e| self.scenario.error(e))?;
+                self.scenario.has_queries = true;
+            }
+            Some('?') => {
+                let line = is_interactive('?', line.to_string(), facts, options)?;
+                if lexer::is_expression(&line) {
+                    let expression = Rules::expression(facts, &line, options)
+                        .map_err(|e| self.scenario.error(e))?;
+                    self.scenario.expressions.push(expression);
+                } else {
+                    facts
+                        .set_queries(&line, options)
+                        .map_err(|e| self.scenario.error(e))?;
                 }
-                Some('#') => {
-                    if options.comment && !options.file {
-                        println!("{}", line);
-                    }
+                self.scenario.has_queries = true;
+            }
+            Some('@') => {
+                if options.comment && !options.file && line.contains('#') {
+                    println!("{}", line);
                 }
-                None => continue,
-                _ => {
+                let next = Scenario::from_line(line)?;
+                if self.scenario.name == next.name
+                    || self.scenarios.iter().any(|s| s.name == next.name)
+                {
                     return Err(Error::new(
                         ErrorKind::InvalidData,
-                        format!("Input file: unexpected char (line: {})", line),
-                    ))
+                        format!("Scenario: doublon (at {})", line),
+                    ));
+                }
+                let mut scenario = std::mem::replace(&mut self.scenario, next);
+                if scenario.name.is_some() || !scenario.is_empty() {
+                    scenario.close(facts)?;
+                    self.scenarios.push(scenario);
+                }
+            }
+            Some('#') => {
+                if options.comment && !options.file {
+                    println!("{}", line);
                 }
             }
+            None => (),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Input file: unexpected char (line: {})", line),
+                ))
+            }
         }
+        Ok(())
     }
-    if !has_initial_facts {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Parser: no initial fact",
-        ));
+}
+
+fn parser<'a>(
+    source: &Source,
+    facts: &'a Facts,
+    options: &Options,
+) -> Result<(Rules<'a>, Vec<Scenario<'a>>), Error> {
+    let mut parser = Parser::new(facts);
+    if options.file {
+        println!("=== FILE ===");
+    } else if options.comment {
+        println!("=== COMMENT ===");
+    }
+    for line in source.lines.iter() {
+        if options.file {
+            println!("{}", line.text);
+        }
+        facts.set_scope(line.module.clone());
+        parser.rules.set_location(Some(line.location()));
+        parser
+            .parse_line(&line.text, options)
+            .map_err(|e| line.error(e))?;
     }
-    Ok(rules)
+    parser.scenario.close(facts)?;
+    parser.scenarios.push(parser.scenario);
+    Ok((parser.rules, parser.scenarios))
+}
+
+// Rules and scenarios of a loaded source, ready to be solved.
+pub fn parse_source<'a>(
+    source: &Source,
+    facts: &'a Facts,
+    options: &Options,
+) -> Result<(Rules<'a>, Vec<Scenario<'a>>), Error> {
+    let (mut rules, scenarios) = parser(source, facts, options)?;
+    rules.as_reverse_polish_notation()?;
+    Ok((rules, scenarios))
 }
 
-fn queries_of_parsed(facts: &Facts) -> Vec<&Fact> {
+// Rules of a loaded source, checked as for solving it.
+pub fn rules_of_source<'a>(
+    source: &Source,
+    facts: &'a Facts,
+    options: &Options,
+) -> Result<Rules<'a>, Error> {
+    parse_source(source, facts, options).map(|(rules, _)| rules)
+}
+
+pub fn queries_of_parsed(facts: &Facts) -> Vec<&Fact> {
     let mut queries = Vec::new();
     for fact in facts.fact_arr.iter() {
         if fact.queried.get() {
@@ -83,27 +189,164 @@ fn queries_of_parsed(facts: &Facts) -> Vec<&Fact> {
     queries
 }
 
-fn expert_system(file: File, options: &Options) -> Result<Vec<Fact>, Error> {
-    let facts = Facts::new();
-    let mut rules = parser(file, &facts, options)?;
-    rules.as_reverse_polish_notation()?;
-    let queries = queries_of_parsed(&facts);
-    if queries.is_empty() {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "No queries provided, tho nothing to solve",
-        ));
+// How facts are valued, chosen once from the options.
+enum Evaluator<'a> {
+    Boolean(Template),
+    Weighted(Weighted<'a>),
+    Probability(Probability<'a>),
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(facts: &'a Facts, rules: &'a Rules<'a>, options: &'a Options) -> Result<Self, Error> {
+        let algebra: &dyn Algebra = match &options.fuzzy {
+            Some(norm) => norm,
+            None if options.certainty => &Mycin,
+            None if options.probability => {
+                return Ok(Evaluator::Probability(Probability::new(
+                    facts, rules, options,
+                )))
+            }
+            None => {
+                let template = match &options.template {
+                    Some(path) if options.explain > 0 => Template::load(path)?,
+                    _ => Template::default(),
+                };
+                return Ok(Evaluator::Boolean(template));
+            }
+        };
+        Ok(Evaluator::Weighted(Weighted::new(rules, options, algebra)))
     }
-    let solved_queries: Vec<Fact> = solver::solve(queries, rules, options)?;
-    Ok(solved_queries)
+
+    // Solved queries, expressions and explanation of a scenario, its facts
+    // being set.
+    fn solve(
+        &self,
+        scenario: &Scenario<'a>,
+        queries: Vec<&'a Fact>,
+        rules: &'a Rules<'a>,
+        options: &'a Options,
+    ) -> Result<ScenarioResult, Error> {
+        let (solved_queries, expressions, explanation) = match self {
+            Evaluator::Boolean(template) => {
+                let solved_queries = solver::solve(queries.clone(), rules, options)?;
+                let mut expressions = Vec::new();
+                for expression in scenario.expressions.iter() {
+                    let value = solver::solve_expression(expression, rules, options)?;
+                    expressions.push((expression.label.clone(), State::from_value(value), None));
+                }
+                let explainer = Explainer {
+                    rules,
+                    initial: &scenario.facts,
+                    template,
+                    options,
+                };
+                let explanation = match options.explain {
+                    0 => String::new(),
+                    _ => explainer.explain(&queries),
+                };
+                (solved_queries, expressions, explanation)
+            }
+            Evaluator::Weighted(weighted) => {
+                weighted.reset();
+                let solved_queries = weighted.solve(queries);
+                let expressions = scenario
+                    .expressions
+                    .iter()
+                    .map(|expression| {
+                        let (state, measure) = weighted.expression(&expression.lhs);
+                        (expression.label.clone(), state, Some(measure))
+                    })
+                    .collect();
+                (solved_queries, expressions, weighted.trace.explanation())
+            }
+            // Each query is true with the probability computed over the
+            // worlds of its uncertain facts.
+            Evaluator::Probability(probability) => {
+                let values =
+                    probability.solve(&scenario.facts, queries.clone(), &scenario.expressions)?;
+                let measure = |value: f64| match probability.sampled.get() {
+                    true => Measure::Estimate(value),
+                    false => Measure::Probability(value),
+                };
+                let solved_queries = queries
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(fact, value)| {
+                        let solved = fact.copy();
+                        let state = weighted::state(*value, 0.5, 0.5, options);
+                        solved.state.set(state == State::True);
+                        solved.determined.set(state != State::Undetermined);
+                        solved.measure.set(Some(measure(*value)));
+                        solved
+                    })
+                    .collect();
+                let expressions = scenario
+                    .expressions
+                    .iter()
+                    .zip(values[queries.len()..].iter())
+                    .map(|(expression, value)| {
+                        let state = weighted::state(*value, 0.5, 0.5, options);
+                        (expression.label.clone(), state, Some(measure(*value)))
+                    })
+                    .collect();
+                (solved_queries, expressions, probability.trace.explanation())
+            }
+        };
+        Ok(ScenarioResult {
+            name: scenario.name.clone(),
+            solved_queries,
+            expressions,
+            explanation: match options.explain {
+                0 => String::new(),
+                _ => explanation,
+            },
+        })
+    }
+}
+
+fn expert_system(path: &Path, options: &Options) -> Result<Vec<ScenarioResult>, Error> {
+    let source = Source::load(path)?;
+    let facts = Facts::from_source(&source);
+    let (rules, scenarios) = parse_source(&source, &facts, options)?;
+    let evaluator = Evaluator::new(&facts, &rules, options)?;
+    let mut results = Vec::new();
+    for scenario in scenarios.iter() {
+        facts.restore(&scenario.facts);
+        let queries = queries_of_parsed(&facts);
+        if queries.is_empty() && scenario.expressions.is_empty() {
+            return Err(scenario.error(Error::new(
+                ErrorKind::InvalidData,
+                "No queries provided, tho nothing to solve",
+            )));
+        }
+        if let Some(name) = &scenario.name {
+            if options.graph {
+                println!("=== SCENARIO {} ===", name);
+            }
+        }
+        let result = evaluator
+            .solve(scenario, queries, &rules, options)
+            .map_err(|e| scenario.error(e))?;
+        results.push(result);
+    }
+    if let Some(dir) = &options.dot {
+        match dot::to_file(dir, "rules.dot", &dot::rules_to_dot(&rules)) {
+            Ok(path) => println!(
+                "The rules graph has been printed in the following file : {}",
+                path.display()
+            ),
+            Err(error) => eprintln!("Error: {:?}", error.to_string()),
+        }
+    }
+    Ok(results)
 }
 
-fn expert_system_wrapper(file: File, options: &Options) {
-    match expert_system(file, options) {
-        Ok(solved_queries) => {
-            print::results(&solved_queries);
+fn expert_system_wrapper(path: &Path, options: &Options) {
+    match expert_system(path, options) {
+        Ok(results) => {
+            print::results(&results);
             if options.log {
-                match print::solved_to_file("log", &solved_queries) {
+                match print::solved_to_file("log", &results) {
                     Ok(_) => {
                         println!("The output result has been printed in the following file : log")
                     }
@@ -111,11 +354,18 @@ fn expert_system_wrapper(file: File, options: &Options) {
                 }
             }
         }
-        Err(error) => eprintln!(
-            "Oops, something went wrong, shutting program down.\nError: {:?}",
-            error.to_string()
-        ),
+        Err(error) => eprint!("{}", print::error_to_string(&error)),
+    }
+}
+
+pub fn solve_file(filename: &str, options: &Options) -> Result<Vec<ScenarioResult>, Error> {
+    if Path::new(filename).is_dir() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("open: {}: Is a directory", filename),
+        ));
     }
+    expert_system(Path::new(filename), options)
 }
 
 pub fn run(filename: &str, options: &Options) {
@@ -123,7 +373,7 @@ pub fn run(filename: &str, options: &Options) {
         println!("open: {}: Is a directory", filename);
     } else {
         match File::open(filename) {
-            Ok(file) => expert_system_wrapper(file, options),
+            Ok(_) => expert_system_wrapper(Path::new(filename), options),
             Err(error) => eprintln!("open: {}: {:?}", filename, error.to_string()),
         };
     }
@@ -0,0 +1,244 @@
+use crate::facts::Fact;
+use crate::options::Options;
+use crate::rules::Rules;
+use crate::solver;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{prelude::*, BufReader, Error, ErrorKind};
+
+// Sentences of an explanation, {name} placeholders are filled when rendering.
+pub struct Template {
+    pub derived: String,
+    pub unconditional: String,
+    pub initial: String,
+    pub default: String,
+    pub undetermined: String,
+    pub premise: String,
+    pub premises: String,
+    pub separator: String,
+    pub described: String,
+    pub value_true: String,
+    pub value_false: String,
+    pub value_undetermined: String,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template {
+            derived: String::from("{fact} is {value} because {premises} (rule {rule}: {infix})."),
+            unconditional: String::from(
+                "{fact} is {value} because rule {rule} has no condition ({infix}).",
+            ),
+            initial: String::from("{fact} is {value} because it is an initial fact."),
+            default: String::from("{fact} is {value} because no rule concluding it holds."),
+            undetermined: String::from("{fact} is {value} because nothing establishes it."),
+            premise: String::from("{fact} is {value}"),
+            premises: String::from("{facts} are {value}"),
+            separator: String::from(" and "),
+            described: String::from("{fact} ({description})"),
+            value_true: String::from("true"),
+            value_false: String::from("false"),
+            value_undetermined: String::from("undetermined"),
+        }
+    }
+}
+
+fn fill(text: &str, values: &[(&str, &str)]) -> String {
+    let mut text = text.to_string();
+    for (key, value) in values.iter() {
+        text = text.replace(&format!("{{{}}}", key), value);
+    }
+    text
+}
+
+impl Template {
+    // Reads "key = value" lines, a value can be quoted to keep its spaces.
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let mut template = Template::default();
+        let reader = BufReader::new(File::open(path)?);
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| {
+                Error::new(e.kind(), format!("Template: {} ({}:{})", e, path, i + 1))
+            })?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Template: expected key = value ({}:{})", path, i + 1),
+                    ))
+                }
+            };
+            let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(quoted) => quoted.to_string(),
+                None => value.to_string(),
+            };
+            let field = match key {
+                "derived" => &mut template.derived,
+                "unconditional" => &mut template.unconditional,
+                "initial" => &mut template.initial,
+                "default" => &mut template.default,
+                "undetermined" => &mut template.undetermined,
+                "premise" => &mut template.premise,
+                "premises" => &mut template.premises,
+                "separator" => &mut template.separator,
+                "described" => &mut template.described,
+                "true" => &mut template.value_true,
+                "false" => &mut template.value_false,
+                "undetermined_value" => &mut template.value_undetermined,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Template: unknown key {} ({}:{})", key, path, i + 1),
+                    ))
+                }
+            };
+            *field = value;
+        }
+        Ok(template)
+    }
+
+    fn value(&self, value: Option<bool>) -> &str {
+        match value {
+            Some(true) => &self.value_true,
+            Some(false) => &self.value_false,
+            None => &self.value_undetermined,
+        }
+    }
+
+    fn label(&self, fact: &Fact) -> String {
+        match &fact.description {
+            Some(description) => fill(
+                &self.described,
+                &[("fact", &fact.name), ("description", description)],
+            ),
+            None => fact.name.clone(),
+        }
+    }
+}
+
+pub struct Explainer<'a> {
+    pub rules: &'a Rules<'a>,
+    // Facts as they were before solving, to tell initial facts apart.
+    pub initial: &'a [Fact],
+    pub template: &'a Template,
+    pub options: &'a Options,
+}
+
+impl<'a> Explainer<'a> {
+    fn is_initial(&self, fact: &Fact) -> bool {
+        self.initial
+            .iter()
+            .any(|saved| saved.name == fact.name && saved.determined.get())
+    }
+
+    // Facts of the rule body in the order they are written.
+    fn premise_facts(&self, index: usize) -> Vec<&'a Fact> {
+        let mut facts: Vec<&Fact> = Vec::new();
+        if let Some(rule) = self.rules.get(index) {
+            for token in rule.lhs.iter().rev() {
+                if let Some(fact) = token.fact {
+                    if !facts.iter().any(|f| f.name == fact.name) {
+                        facts.push(fact);
+                    }
+                }
+            }
+        }
+        facts
+    }
+
+    fn premises(&self, index: usize) -> String {
+        let mut groups: Vec<(Option<bool>, Vec<String>)> = Vec::new();
+        for fact in self.premise_facts(index) {
+            let value = solver::fact_value(fact, self.options);
+            let label = self.template.label(fact);
+            match groups.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, labels)) => labels.push(label),
+                None => groups.push((value, vec![label])),
+            }
+        }
+        let premises: Vec<String> = groups
+            .iter()
+            .map(|(value, labels)| {
+                let value = self.template.value(*value);
+                if labels.len() == 1 {
+                    fill(
+                        &self.template.premise,
+                        &[("fact", &labels[0]), ("value", value)],
+                    )
+                } else {
+                    let facts = labels.join(&self.template.separator);
+                    fill(
+                        &self.template.premises,
+                        &[("facts", &facts), ("value", value)],
+                    )
+                }
+            })
+            .collect();
+        premises.join(&self.template.separator)
+    }
+
+    pub fn sentence(&self, fact: &Fact) -> String {
+        let value = solver::fact_value(fact, self.options);
+        let label = self.template.label(fact);
+        let values = [
+            ("fact", label.as_str()),
+            ("value", self.template.value(value)),
+        ];
+        match (fact.rule.get(), value) {
+            (Some(index), _) => {
+                let premises = self.premises(index);
+                let rule = self.rules.get(index);
+                let infix = rule.map(|r| r.to_infix()).unwrap_or_default();
+                let rule = rule.map(|r| r.provenance()).unwrap_or_default();
+                let mut values = values.to_vec();
+                values.extend_from_slice(&[
+                    ("premises", &premises),
+                    ("rule", &rule),
+                    ("infix", &infix),
+                ]);
+                if self.premise_facts(index).is_empty() {
+                    fill(&self.template.unconditional, &values)
+                } else {
+                    fill(&self.template.derived, &values)
+                }
+            }
+            (None, None) => fill(&self.template.undetermined, &values),
+            (None, Some(_)) if self.is_initial(fact) => fill(&self.template.initial, &values),
+            (None, Some(_)) => fill(&self.template.default, &values),
+        }
+    }
+
+    fn explain_rec(&self, fact: &Fact, depth: usize, seen: &mut HashSet<String>, out: &mut String) {
+        if !seen.insert(fact.name.clone()) {
+            return;
+        }
+        out.push_str(&format!("{:1$}{2}\n", "", depth * 2, self.sentence(fact)));
+        if self.options.explain < 2 {
+            return;
+        }
+        if let Some(index) = fact.rule.get() {
+            for premise in self.premise_facts(index) {
+                if premise.rule.get().is_some() || self.options.explain >= 3 {
+                    self.explain_rec(premise, depth + 1, seen, out);
+                }
+            }
+        }
+    }
+
+    // Verbosity 1 explains the queries only, 2 walks the derived premises
+    // down to the initial facts and 3 explains every premise.
+    pub fn explain(&self, queries: &[&Fact]) -> String {
+        let mut out = String::new();
+        for fact in queries.iter() {
+            let mut seen = HashSet::new();
+            self.explain_rec(fact, 0, &mut seen, &mut out);
+        }
+        out
+    }
+}
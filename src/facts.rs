@@ -1,6 +1,84 @@
+use crate::checker;
+use crate::lexer;
 use crate::options::Options;
-use std::cell::Cell;
+use crate::source::Source;
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::{Error, ErrorKind};
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum State {
+    True,
+    False,
+    Undetermined,
+}
+
+impl State {
+    pub fn from_word(word: &str) -> Option<State> {
+        match word.to_lowercase().as_str() {
+            "true" => Some(State::True),
+            "false" => Some(State::False),
+            "undetermined" => Some(State::Undetermined),
+            _ => None,
+        }
+    }
+
+    pub fn from_value(value: Option<bool>) -> State {
+        match value {
+            Some(true) => State::True,
+            Some(false) => State::False,
+            None => State::Undetermined,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            State::True => "True",
+            State::False => "False",
+            State::Undetermined => "Undetermined",
+        }
+    }
+}
+
+// Value of a query solved by one of the weighted modes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Measure {
+    Certainty(f64),
+    Degree(f64),
+    Probability(f64),
+    // Probability estimated by sampling.
+    Estimate(f64),
+}
+
+// Fact read as "temperature > 38", true when the numeric fact compares so with
+// the number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub fact: String,
+    pub operator: &'static str,
+    pub threshold: f64,
+}
+
+impl Comparison {
+    pub fn name(&self) -> String {
+        format!("{} {} {}", self.fact, self.operator, self.threshold)
+    }
+
+    pub fn holds(&self, value: f64) -> bool {
+        match self.operator {
+            ">" => value > self.threshold,
+            ">=" => value >= self.threshold,
+            "<" => value < self.threshold,
+            "<=" => value <= self.threshold,
+            "==" => value == self.threshold,
+            _ => value != self.threshold,
+        }
+    }
+}
 
 #[derive(Clone, Debug, Default)]
 pub struct Fact {
@@ -8,17 +86,35 @@ pub struct Fact {
     pub reverse_state: Cell<bool>,
     pub determined: Cell<bool>,
     pub queried: Cell<bool>,
-    pub letter: char,
+    pub expected: Cell<Option<State>>,
+    // Index of the rule which established the fact, None for initial facts.
+    pub rule: Cell<Option<usize>>,
+    // Certainty factor, in [-1, 1], of an initial fact or a solved query.
+    pub certainty: Cell<Option<f64>>,
+    // How sure a query solved with --certainty, --fuzzy or --probability is.
+    pub measure: Cell<Option<Measure>>,
+    // Value of a numeric fact, "temperature = 39.2".
+    pub value: Cell<Option<f64>>,
+    pub name: String,
+    pub description: Option<String>,
+    pub comparison: Option<Comparison>,
 }
 
 impl Fact {
-    pub fn new(letter: char) -> Self {
+    pub fn new(name: &str) -> Self {
         Fact {
             state: Cell::new(false),
             reverse_state: Cell::new(false),
             determined: Cell::new(false),
             queried: Cell::new(false),
-            letter,
+            expected: Cell::new(None),
+            rule: Cell::new(None),
+            certainty: Cell::new(None),
+            measure: Cell::new(None),
+            value: Cell::new(None),
+            name: name.to_string(),
+            description: None,
+            comparison: None,
         }
     }
 
@@ -28,10 +124,46 @@ impl Fact {
             reverse_state: Cell::new(self.reverse_state.get()),
             determined: Cell::new(self.determined.get()),
             queried: Cell::new(self.queried.get()),
-            letter: self.letter,
+            expected: Cell::new(self.expected.get()),
+            rule: Cell::new(self.rule.get()),
+            certainty: Cell::new(self.certainty.get()),
+            measure: Cell::new(self.measure.get()),
+            value: Cell::new(self.value.get()),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            comparison: self.comparison.clone(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.description {
+            Some(description) => format!("{} ({})", self.name, description),
+            None => self.name.clone(),
+        }
+    }
+
+    pub fn solved_state(&self) -> State {
+        if !self.determined.get() {
+            State::Undetermined
+        } else if self.state.get() {
+            State::True
+        } else {
+            State::False
         }
     }
 
+    pub fn restore(&self, saved: &Fact) {
+        self.state.set(saved.state.get());
+        self.reverse_state.set(saved.reverse_state.get());
+        self.determined.set(saved.determined.get());
+        self.queried.set(saved.queried.get());
+        self.expected.set(saved.expected.get());
+        self.rule.set(saved.rule.get());
+        self.certainty.set(saved.certainty.get());
+        self.measure.set(saved.measure.get());
+        self.value.set(saved.value.get());
+    }
+
     pub fn set_solved(&self, mut expr_result: bool) {
         if self.reverse_state.get() {
             expr_result = !expr_result;
@@ -46,58 +178,294 @@ impl Fact {
 
 #[derive(Default)]
 pub struct Facts {
-    pub fact_arr: [Fact; 26],
+    pub fact_arr: Vec<Fact>,
     pub is_stable: bool,
+    symbols: HashMap<String, usize>,
+    // Exported facts of each module, None when every fact is visible.
+    modules: BTreeMap<String, Option<BTreeSet<String>>>,
+    // Module of the line being parsed, unqualified names are looked up in it.
+    scope: RefCell<Option<Rc<String>>>,
+}
+
+fn qualify(name: &str, module: Option<&str>) -> String {
+    match module {
+        Some(module) if !name.contains('.') => format!("{}.{}", module, name),
+        _ => name.to_string(),
+    }
 }
 
 impl Facts {
     pub fn new() -> Self {
-        let arr = [
-            Fact::new('A'),
-            Fact::new('B'),
-            Fact::new('C'),
-            Fact::new('D'),
-            Fact::new('E'),
-            Fact::new('F'),
-            Fact::new('G'),
-            Fact::new('H'),
-            Fact::new('I'),
-            Fact::new('J'),
-            Fact::new('K'),
-            Fact::new('L'),
-            Fact::new('M'),
-            Fact::new('N'),
-            Fact::new('O'),
-            Fact::new('P'),
-            Fact::new('Q'),
-            Fact::new('R'),
-            Fact::new('S'),
-            Fact::new('T'),
-            Fact::new('U'),
-            Fact::new('V'),
-            Fact::new('W'),
-            Fact::new('X'),
-            Fact::new('Y'),
-            Fact::new('Z'),
-        ];
+        Facts::with_names(BTreeSet::new())
+    }
+
+    // Facts are all created before parsing since rules borrow them, so the
+    // names met in the source are collected beforehand.
+    pub fn from_source(source: &Source) -> Self {
+        let mut names = BTreeSet::new();
+        let mut descriptions = HashMap::new();
+        let mut comparisons = HashMap::new();
+        for line in source.lines.iter() {
+            let text = line.text.trim();
+            let module = line.module.as_ref().map(|m| m.as_str());
+            if let Some((name, description)) = lexer::declaration(text) {
+                names.insert(qualify(name, module));
+                descriptions.insert(qualify(name, module), description.to_string());
+                continue;
+            }
+            let words = match text.chars().next() {
+                Some('#') | Some('@') | None => continue,
+                Some('?') if text.starts_with("?=") => lexer::names(text)
+                    .into_iter()
+                    .filter(|name| State::from_word(name).is_none())
+                    .collect(),
+                _ => lexer::names(text),
+            };
+            for name in words {
+                names.insert(qualify(&name, module));
+            }
+            for (name, operator, threshold) in lexer::comparisons(text) {
+                let comparison = Comparison {
+                    fact: qualify(&name, module),
+                    operator,
+                    threshold,
+                };
+                names.insert(comparison.name());
+                comparisons.insert(comparison.name(), comparison);
+            }
+        }
+        let mut facts = Facts::with_names(names);
+        for fact in facts.fact_arr.iter_mut() {
+            fact.description = descriptions.remove(&fact.name);
+            fact.comparison = comparisons.remove(&fact.name);
+        }
+        facts.modules = source.modules.clone();
+        facts
+    }
+
+    // Facts of a file read on its own, as the formatter does: names are
+    // kept as written and every module they refer to is fully visible.
+    pub fn from_text(text: &str) -> Self {
+        let mut names = BTreeSet::new();
+        let mut comparisons = HashMap::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some((name, _)) = lexer::declaration(line) {
+                names.insert(name.to_string());
+                continue;
+            }
+            match line.chars().next() {
+                Some('#') | Some('@') | None => continue,
+                _ => names.extend(lexer::names(line)),
+            }
+            for (name, operator, threshold) in lexer::comparisons(line) {
+                let comparison = Comparison {
+                    fact: name,
+                    operator,
+                    threshold,
+                };
+                names.insert(comparison.name());
+                comparisons.insert(comparison.name(), comparison);
+            }
+        }
+        let mut facts = Facts::with_names(names);
+        for fact in facts.fact_arr.iter_mut() {
+            fact.comparison = comparisons.remove(&fact.name);
+        }
+        for name in facts.symbols.keys() {
+            if let Some((module, _)) = name.rsplit_once('.') {
+                facts.modules.insert(module.to_string(), None);
+            }
+        }
+        facts
+    }
 
+    fn with_names(mut names: BTreeSet<String>) -> Self {
+        for letter in 'A'..='Z' {
+            names.insert(letter.to_string());
+        }
+        let fact_arr: Vec<Fact> = names.iter().map(|name| Fact::new(name)).collect();
+        let symbols = names.into_iter().enumerate().map(|(i, n)| (n, i)).collect();
         Facts {
-            fact_arr: arr,
-            is_stable: false,
+            fact_arr,
+            symbols,
+            ..Default::default()
         }
     }
 
-    pub fn get(&self, letter: char) -> &Fact {
-        &self.fact_arr[self.get_index(letter)]
+    pub fn get(&self, name: &str) -> Option<&Fact> {
+        self.symbols.get(name).map(|&index| &self.fact_arr[index])
+    }
+
+    pub fn set_scope(&self, module: Option<Rc<String>>) {
+        *self.scope.borrow_mut() = module;
+    }
+
+    // Resolves a name as written in the current module, qualified names of
+    // other modules have to be exported by them.
+    pub fn lookup(&self, name: &str) -> Result<&Fact, Error> {
+        if !lexer::is_valid_name(name) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Facts: invalid name ({})", name),
+            ));
+        }
+        let scope = self.scope.borrow();
+        let qualified = qualify(name, scope.as_ref().map(|m| m.as_str()));
+        if let Some((module, fact)) = qualified.rsplit_once('.') {
+            if scope.as_ref().map(|m| m.as_str()) != Some(module) {
+                match self.modules.get(module) {
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Facts: unknown module ({})", qualified),
+                        ))
+                    }
+                    Some(Some(exports)) if !exports.contains(fact) => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Facts: {} is not exported by module {}", qualified, module),
+                        ))
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.get(&qualified).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Facts: unknown fact ({})", qualified),
+            )
+        })
+    }
+
+    // Fact of a comparison read in a rule or a query, "temperature > 38".
+    pub fn comparison(
+        &self,
+        name: &str,
+        operator: &'static str,
+        threshold: f64,
+    ) -> Result<&Fact, Error> {
+        let comparison = Comparison {
+            fact: self.lookup(name)?.name.clone(),
+            operator,
+            threshold,
+        };
+        self.get(&comparison.name()).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Facts: unknown comparison ({})", comparison.name()),
+            )
+        })
+    }
+
+    // Gives a numeric fact its value, the comparisons reading it are then
+    // determined.
+    pub fn set_value(&self, name: &str, value: f64) -> Result<(), Error> {
+        let fact = self.lookup(name)?;
+        if fact.comparison.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Facts: {} is a comparison", name),
+            ));
+        }
+        fact.value.set(Some(value));
+        for comparison in self.fact_arr.iter() {
+            match &comparison.comparison {
+                Some(c) if c.fact == fact.name => {
+                    comparison.state.set(c.holds(value));
+                    comparison.determined.set(true);
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_facts(
+        &self,
+        first: char,
+        chars: &mut Peekable<Chars>,
+    ) -> Result<Vec<&Fact>, Error> {
+        self.name_facts(&lexer::read_name(first, chars))
+    }
+
+    pub fn name_facts(&self, name: &str) -> Result<Vec<&Fact>, Error> {
+        lexer::split_name(name)
+            .iter()
+            .map(|name| self.lookup(name))
+            .collect()
+    }
+
+    pub fn declare(&self, line: &str) -> Result<&Fact, Error> {
+        let (name, description) = match lexer::declaration(line) {
+            Some(declaration) => declaration,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Declarations: expected name: description",
+                ))
+            }
+        };
+        if lexer::split_name(name).len() != 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Declarations: one fact per declaration (at {})", name),
+            ));
+        }
+        if description.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Declarations: missing description (at {})", name),
+            ));
+        }
+        self.lookup(name)
+    }
+
+    pub fn snapshot(&self) -> Vec<Fact> {
+        self.fact_arr.iter().map(Fact::copy).collect()
+    }
+
+    pub fn restore(&self, snapshot: &[Fact]) {
+        for (fact, saved) in self.fact_arr.iter().zip(snapshot.iter()) {
+            fact.restore(saved);
+        }
+    }
+
+    pub fn reset(&self) {
+        for fact in self.fact_arr.iter() {
+            fact.restore(&Fact::new(&fact.name));
+        }
+    }
+
+    // "temperature = 39.2" on the initial facts line.
+    fn initial_value(&self, name: &str, number: &str, is_not: bool) -> Result<(), Error> {
+        let invalid = |message: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Initial facts: {} (at {} = {})", message, name, number),
+            )
+        };
+        let value = number.parse().map_err(|_| invalid("invalid value"))?;
+        if is_not || lexer::split_name(name).len() > 1 {
+            return Err(invalid("value of a negated or multiple fact"));
+        }
+        if self.lookup(name)?.value.get().is_some() {
+            return Err(invalid("doublon"));
+        }
+        self.set_value(name, value)
     }
 
     pub fn set_initial_facts(&self, line: &str, options: &Options) -> Result<(), Error> {
-        let mut chars = line.chars();
+        let mut chars = line.chars().peekable();
+        let mut is_not = false;
+        // Facts of the last word, a certainty factor after it applies to them.
+        let mut last: Vec<&Fact> = Vec::new();
         if !options.interactive || options.file || options.comment {
             chars.next();
         }
-        for c in chars {
-            if c.is_whitespace() {
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() && !is_not {
                 continue;
             }
             if c == '>' {
@@ -107,15 +475,54 @@ impl Facts {
                 ));
             }
             match c {
-                'A'..='Z' => {
-                    let fact = &self.fact_arr[self.get_index(c)];
-                    if fact.state.get() {
-                        return Err(Error::new(ErrorKind::InvalidData, "Initial facts: doublon"));
+                '!' if !is_not => is_not = true,
+                c if lexer::is_name_start(c) => {
+                    let name = lexer::read_name(c, &mut chars);
+                    let rest: String = chars.clone().collect();
+                    if let Some((number, len)) = lexer::value(&rest) {
+                        self.initial_value(&name, number, is_not)?;
+                        chars.nth(rest[..len].chars().count() - 1);
+                        last = Vec::new();
+                        continue;
+                    }
+                    last = self.name_facts(&name)?;
+                    for fact in last.iter() {
+                        if fact.determined.get() {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "Initial facts: doublon",
+                            ));
+                        }
+                        fact.state.set(!is_not);
+                        fact.determined.set(true);
+                        is_not = false;
                     }
-                    fact.state.set(true);
-                    fact.determined.set(true);
                 }
-                '#' => {
+                '[' if !is_not => {
+                    let mut factor = String::new();
+                    while let Some(c) = chars.next_if(|c| *c != ']') {
+                        factor.push(c);
+                    }
+                    if chars.next().is_none() {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "Initial facts: unclosed certainty factor",
+                        ));
+                    }
+                    let factor = checker::certainty(&factor, options)?;
+                    if last.is_empty() || last.iter().any(|fact| fact.certainty.get().is_some()) {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "Initial facts: certainty factor without fact",
+                        ));
+                    }
+                    // A negated fact is false with that certainty.
+                    for fact in last.iter() {
+                        let sign = if fact.state.get() { 1.0 } else { -1.0 };
+                        fact.certainty.set(Some(sign * factor));
+                    }
+                }
+                '#' if !is_not => {
                     if options.comment && !options.file {
                         println!("{}", line);
                     }
@@ -129,25 +536,32 @@ impl Facts {
                 }
             }
         }
+        if is_not {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Initial facts: unexpected char",
+            ));
+        }
         Ok(())
     }
 
     pub fn set_queries(&self, line: &str, options: &Options) -> Result<(), Error> {
-        let mut chars = line.chars();
+        let mut chars = line.chars().peekable();
         if !options.interactive || options.file || options.comment {
             chars.next();
         }
-        for c in chars {
+        while let Some(c) = chars.next() {
             if c.is_whitespace() {
                 continue;
             }
             match c {
-                'A'..='Z' => {
-                    let fact = &self.fact_arr[self.get_index(c)];
-                    if fact.queried.get() {
-                        return Err(Error::new(ErrorKind::InvalidData, "Queries: doublon"));
+                c if lexer::is_name_start(c) => {
+                    for fact in self.read_facts(c, &mut chars)? {
+                        if fact.queried.get() {
+                            return Err(Error::new(ErrorKind::InvalidData, "Queries: doublon"));
+                        }
+                        fact.queried.set(true);
                     }
-                    fact.queried.set(true);
                 }
                 '#' => {
                     if options.comment && !options.file {
@@ -166,20 +580,58 @@ impl Facts {
         Ok(())
     }
 
-    pub fn print(&self, letter: char) {
-        let index = self.get_index(letter);
-        let res = &self.fact_arr[index];
-        println!(
-            "print parser element : {} (index {}) =>\n{:?}",
-            letter, index, res
-        );
+    pub fn set_expectations(&self, line: &str, options: &Options) -> Result<(), Error> {
+        let content = match line.trim().find('#') {
+            Some(comment) => {
+                if options.comment && !options.file {
+                    println!("{}", line);
+                }
+                &line.trim()[2..comment]
+            }
+            None => &line.trim()[2..],
+        };
+        let mut pending: Vec<&Fact> = Vec::new();
+        for word in content.split_whitespace() {
+            if let Some(expected) = State::from_word(word) {
+                if pending.is_empty() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "Expectations: value without fact",
+                    ));
+                }
+                for fact in pending.drain(..) {
+                    fact.expected.set(Some(expected));
+                    fact.queried.set(true);
+                }
+                continue;
+            }
+            let mut chars = word.chars().peekable();
+            while let Some(c) = chars.next() {
+                if !lexer::is_name_start(c) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "Expectations: unexpected char",
+                    ));
+                }
+                for fact in self.read_facts(c, &mut chars)? {
+                    if fact.expected.get().is_some() || pending.iter().any(|f| f.name == fact.name)
+                    {
+                        return Err(Error::new(ErrorKind::InvalidData, "Expectations: doublon"));
+                    }
+                    pending.push(fact);
+                }
+            }
+        }
+        if !pending.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Expectations: missing value (true, false or undetermined)",
+            ));
+        }
+        Ok(())
     }
 
-    fn get_index(&self, letter: char) -> usize {
-        if letter.is_uppercase() {
-            letter as usize - 65
-        } else {
-            panic!("[{}] Letter does not match any valid index", letter);
-        }
+    pub fn print(&self, name: &str) {
+        println!("print parser element : {} =>\n{:?}", name, self.get(name));
     }
 }
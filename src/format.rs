@@ -0,0 +1,197 @@
+use crate::diff;
+use crate::facts::Facts;
+use crate::lexer;
+use crate::options::Options;
+use crate::rules::{
+    rule::{
+        is_comparison, rpn,
+        token::{Operand, Token},
+        Rule,
+    },
+    Rules,
+};
+use crate::source::Line;
+use crate::syntax::{self, NodeKind};
+
+use std::fs;
+use std::io::Error;
+use std::rc::Rc;
+
+// Renders an expression stored in reversed RPN, returns it with its top
+// operator to tell the caller whether it needs brackets.
+fn canonical_rec(tokens: &[Token], i: &mut usize) -> (String, Option<Operand>) {
+    let token = match tokens.get(*i) {
+        Some(token) => *token,
+        None => return (String::new(), None),
+    };
+    *i += 1;
+    match token.operand {
+        Some(Operand::Not) => {
+            let compared = is_comparison(tokens.get(*i));
+            let (operand, top) = canonical_rec(tokens, i);
+            match top {
+                Some(op) if !op.is_unary() => (format!("!({})", operand), Some(Operand::Not)),
+                None if compared => (format!("!({})", operand), Some(Operand::Not)),
+                _ => (format!("!{}", operand), Some(Operand::Not)),
+            }
+        }
+        Some(op @ (Operand::AtLeast(k) | Operand::AtMost(k) | Operand::Exactly(k))) => {
+            let (list, _) = canonical_rec(tokens, i);
+            (format!("{}({}, {})", token.get_op_str(), k, list), Some(op))
+        }
+        Some(Operand::Comma) => {
+            let (rhs, _) = canonical_rec(tokens, i);
+            let (lhs, _) = canonical_rec(tokens, i);
+            (format!("{}, {}", lhs, rhs), Some(Operand::Comma))
+        }
+        Some(op) => {
+            let (rhs, rhs_top) = canonical_rec(tokens, i);
+            let (lhs, lhs_top) = canonical_rec(tokens, i);
+            // Operators of a same priority are read from the left, but for
+            // the implication which is read from the right.
+            let lhs = bracket(lhs, lhs_top, op, op == Operand::Imply);
+            let rhs = bracket(rhs, rhs_top, op, op != Operand::Imply);
+            (format!("{} {} {}", lhs, token.get_op_str(), rhs), Some(op))
+        }
+        None => (token.get_token_str(), None),
+    }
+}
+
+fn bracket(text: String, top: Option<Operand>, parent: Operand, on_tie: bool) -> String {
+    let needed = match top {
+        Some(op) if !op.is_unary() => {
+            let (op, parent) = (rpn::op_priority(op), rpn::op_priority(parent));
+            op > parent || (op == parent && on_tie)
+        }
+        _ => false,
+    };
+    if needed {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+// Renders one side of a rule with the brackets its priorities need only.
+pub fn side_to_canonical(tokens: &[Token]) -> String {
+    let mut i = 0;
+    match canonical_rec(tokens, &mut i) {
+        // An implication on its own is the one of the rule.
+        (side, Some(Operand::Imply | Operand::Equiv)) => format!("({})", side),
+        (side, _) => side,
+    }
+}
+
+fn with_comment(code: String, text: &str) -> String {
+    match text.find('#') {
+        Some(comment) if code.is_empty() => text[comment..].trim_end().to_string(),
+        Some(comment) => format!("{} {}", code, text[comment..].trim_end()),
+        None => code,
+    }
+}
+
+fn words(text: &str) -> String {
+    lexer::strip_comment(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn rule_line(facts: &Facts, text: &str) -> Result<String, Error> {
+    let options = Options::new();
+    let mut rules = Rules::new();
+    rules.set_rule(facts, text, &options)?;
+    rules.as_reverse_polish_notation()?;
+    let is_equivalent = rules.iter().count() == 2;
+    let rule: &Rule = rules.iter().last().unwrap();
+    let mut code = String::new();
+    if text.trim_start().starts_with('[') {
+        code.push_str(&format!("[{}] ", rule.label));
+    }
+    code.push_str(&format!(
+        "{} {} {}",
+        side_to_canonical(&rule.lhs),
+        if is_equivalent { "<=>" } else { "=>" },
+        side_to_canonical(&rule.rhs)
+    ));
+    if let Some(certainty) = rule.certainty {
+        code.push_str(&format!(" [{}]", certainty));
+    }
+    Ok(code)
+}
+
+fn format_line(facts: &Facts, kind: NodeKind, text: &str) -> Result<String, Error> {
+    let trimmed = text.trim();
+    let code = match kind {
+        NodeKind::Blank => return Ok(String::new()),
+        NodeKind::Directive => return Ok(trimmed.to_string()),
+        NodeKind::Declaration => {
+            let (name, description) = lexer::declaration(trimmed).unwrap_or_default();
+            format!("{}: {}", name, description)
+        }
+        NodeKind::Rule => rule_line(facts, text)?,
+        // Values of numeric facts are written "temperature = 39.2".
+        NodeKind::InitialFacts => format!("={}", words(&trimmed[1..].replace('=', " = "))),
+        NodeKind::Expectations => format!("?= {}", words(&trimmed[2..])),
+        NodeKind::Queries if lexer::is_expression(trimmed) => {
+            let expression = Rules::expression(facts, text, &Options::new())?;
+            format!("?{}", side_to_canonical(&expression.lhs))
+        }
+        NodeKind::Queries => format!("?{}", words(&trimmed[1..])),
+        NodeKind::Scenario => format!("@ {}", words(&trimmed[1..])),
+        // Comments are kept as written, errors left for the solver to report.
+        _ => return Ok(text.trim_end().to_string()),
+    };
+    Ok(with_comment(code, trimmed))
+}
+
+// Rewrites a knowledge base in canonical form, keeping its comments,
+// blank lines and the order of its lines. A rule written over several
+// lines is joined, the comments of its first lines go above it.
+pub fn format_text(text: &str, name: &str) -> Result<String, Error> {
+    let facts = Facts::from_text(text);
+    let file = Rc::new(name.to_string());
+    let mut out = String::new();
+    let mut number = 1;
+    for node in syntax::parse(text).root.nodes() {
+        let written = node.span.text(text);
+        let lines: Vec<&str> = written.split_inclusive('\n').collect();
+        let (joined, comments) = lexer::join(&lines);
+        for comment in comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        let line = Line {
+            file: Rc::clone(&file),
+            module: None,
+            number,
+            text: joined,
+        };
+        let formatted = format_line(&facts, node.kind, &line.text).map_err(|e| line.error(e))?;
+        out.push_str(&formatted);
+        out.push('\n');
+        number += written.matches('\n').count();
+    }
+    Ok(out)
+}
+
+// `fmt` subcommand: rewrites the file in place, or with --check tells
+// whether it is already formatted.
+pub fn run(filename: &str, options: &Options) -> Result<bool, Error> {
+    let text = fs::read_to_string(filename)?;
+    let formatted = format_text(&text, filename)?;
+    if formatted == text {
+        return Ok(true);
+    }
+    if options.check {
+        println!("Would reformat {}", filename);
+        print!(
+            "{}",
+            diff::unified(&text, &formatted, filename, "formatted")
+        );
+        return Ok(false);
+    }
+    fs::write(filename, formatted)?;
+    println!("Reformatted {}", filename);
+    Ok(true)
+}
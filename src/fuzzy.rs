@@ -0,0 +1,117 @@
+use crate::facts::{Fact, Measure, State};
+use crate::options::Options;
+use crate::rules::rule::token::Operand;
+use crate::weighted::{self, Algebra};
+
+use std::io::{Error, ErrorKind};
+
+// Pair of a t-norm, for AND, and its dual t-conorm, for OR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Norm {
+    // min / max, the Zadeh operators.
+    Minimum,
+    // a * b / a + b - a * b.
+    Product,
+    // max(0, a + b - 1) / min(1, a + b).
+    Lukasiewicz,
+}
+
+impl Norm {
+    pub fn from_name(name: &str) -> Result<Norm, Error> {
+        match name {
+            "" | "min" => Ok(Norm::Minimum),
+            "product" => Ok(Norm::Product),
+            "lukasiewicz" => Ok(Norm::Lukasiewicz),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Options: unknown norm {}, expected min, product or lukasiewicz",
+                    name
+                ),
+            )),
+        }
+    }
+
+    pub fn t_norm(self, a: f64, b: f64) -> f64 {
+        match self {
+            Norm::Minimum => a.min(b),
+            Norm::Product => a * b,
+            Norm::Lukasiewicz => (a + b - 1.0).max(0.0),
+        }
+    }
+
+    pub fn t_conorm(self, a: f64, b: f64) -> f64 {
+        match self {
+            Norm::Minimum => a.max(b),
+            Norm::Product => a + b - a * b,
+            Norm::Lukasiewicz => (a + b).min(1.0),
+        }
+    }
+
+    // NOT is the standard negation 1 - a whatever the norm, the other
+    // operators being written with NOT, AND and OR.
+    pub fn compute(self, operand: Operand, lhs: f64, rhs: f64) -> f64 {
+        match operand {
+            Operand::Not => 1.0 - lhs,
+            Operand::And => self.t_norm(lhs, rhs),
+            Operand::Or => self.t_conorm(lhs, rhs),
+            // (A AND NOT B) OR (NOT A AND B), |A - B| with Lukasiewicz.
+            Operand::Xor => self.t_conorm(self.t_norm(lhs, 1.0 - rhs), self.t_norm(1.0 - lhs, rhs)),
+            Operand::Nand => 1.0 - self.t_norm(lhs, rhs),
+            Operand::Nor => 1.0 - self.t_conorm(lhs, rhs),
+            Operand::Xnor => 1.0 - self.compute(Operand::Xor, lhs, rhs),
+            Operand::Imply => self.t_conorm(1.0 - lhs, rhs),
+            Operand::Equiv => self.compute(Operand::Xnor, lhs, rhs),
+            _ => 0.0,
+        }
+    }
+}
+
+impl Algebra for Norm {
+    fn name(&self) -> &'static str {
+        "degree"
+    }
+
+    fn measure(&self, value: f64) -> Measure {
+        Measure::Degree(value)
+    }
+
+    // One half is undetermined in an open world and false otherwise.
+    fn state(&self, value: f64, options: &Options) -> State {
+        weighted::state(value, 0.5, 0.5, options)
+    }
+
+    fn initial(&self, fact: &Fact) -> f64 {
+        weighted::degree(fact)
+    }
+
+    fn truth(&self, value: bool) -> f64 {
+        if value {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn not(&self, value: f64) -> f64 {
+        1.0 - value
+    }
+
+    fn compute(&self, operand: Operand, lhs: f64, rhs: f64) -> f64 {
+        Norm::compute(*self, operand, lhs, rhs)
+    }
+
+    // Every rule fires, to the degree of its premise bounded by its weight.
+    fn fire(&self, premise: f64, strength: f64) -> Option<f64> {
+        Some(self.t_norm(premise, strength))
+    }
+
+    fn combine(&self, x: f64, y: f64) -> f64 {
+        self.t_conorm(x, y)
+    }
+
+    // What concludes the negation of a fact lowers its degree.
+    fn conclude(&self, positive: f64, negative: f64) -> f64 {
+        self.t_norm(positive, 1.0 - negative)
+    }
+}
@@ -23,7 +23,7 @@ impl<T> Node<T> {
 
     pub fn set_lhs(&mut self, index: NodeIndex) -> Result<NodeIndex, Error> {
         if self.lhs.is_some() {
-            return Err(Error::new(ErrorKind::Other, "Graph: lhs already filled"));
+            return Err(Error::other("Graph: lhs already filled"));
         }
         self.lhs = Some(index);
         Ok(index)
@@ -31,7 +31,7 @@ impl<T> Node<T> {
 
     pub fn set_rhs(&mut self, index: NodeIndex) -> Result<NodeIndex, Error> {
         if self.rhs.is_some() {
-            return Err(Error::new(ErrorKind::Other, "Graph: rhs already filled"));
+            return Err(Error::other("Graph: rhs already filled"));
         }
         self.rhs = Some(index);
         Ok(index)
@@ -39,7 +39,7 @@ impl<T> Node<T> {
 
     pub fn set_parent(&mut self, index: NodeIndex) -> Result<NodeIndex, Error> {
         if self.parent.is_some() {
-            return Err(Error::new(ErrorKind::Other, "Graph: parent already filled"));
+            return Err(Error::other("Graph: parent already filled"));
         }
         self.parent = Some(index);
         Ok(index)
@@ -138,11 +138,11 @@ impl<T> Graph<T> {
         self.0.get_mut(key)
     }
 
-    pub fn iter(&self) -> Iter<Node<T>> {
+    pub fn iter(&self) -> Iter<'_, Node<T>> {
         self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<Node<T>> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, Node<T>> {
         self.0.iter_mut()
     }
 
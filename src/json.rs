@@ -0,0 +1,281 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::iter::Peekable;
+use std::str::Chars;
+
+// JSON document, objects keep the order of their members.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+// Arrays and objects nested deeper than this are refused.
+const MAX_DEPTH: usize = 64;
+
+fn error(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Json: {}", message))
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn expect_word(chars: &mut Peekable<Chars>, word: &str, value: Value) -> Result<Value, Error> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(error("unexpected word"));
+        }
+    }
+    Ok(value)
+}
+
+fn hex4(chars: &mut Peekable<Chars>) -> Result<u32, Error> {
+    let mut code = 0;
+    for _ in 0..4 {
+        let digit = chars.next().and_then(|c| c.to_digit(16));
+        code = code * 16 + digit.ok_or_else(|| error("invalid \\u escape"))?;
+    }
+    Ok(code)
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, Error> {
+    let mut out = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(out),
+            Some('\\') => {
+                let c = match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('u') => {
+                        let mut code = hex4(chars)?;
+                        // A surrogate pair stands for one char out of the BMP.
+                        if (0xD800..0xDC00).contains(&code) {
+                            if chars.next() != Some('\\') || chars.next() != Some('u') {
+                                return Err(error("unpaired surrogate"));
+                            }
+                            let low = hex4(chars)?;
+                            code = low
+                                .checked_sub(0xDC00)
+                                .filter(|low| *low < 0x400)
+                                .map(|low| 0x10000 + ((code - 0xD800) << 10) + low)
+                                .ok_or_else(|| error("unpaired surrogate"))?;
+                        } else if (0xDC00..0xE000).contains(&code) {
+                            return Err(error("unpaired surrogate"));
+                        }
+                        char::from_u32(code).ok_or_else(|| error("invalid \\u escape"))?
+                    }
+                    Some(c @ ('"' | '\\' | '/')) => c,
+                    _ => return Err(error("invalid escape")),
+                };
+                out.push(c);
+            }
+            Some(c) => out.push(c),
+            None => return Err(error("unterminated string")),
+        }
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Result<Value, Error> {
+    let mut text = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+        text.push(c);
+    }
+    text.parse::<f64>()
+        .map(Value::Number)
+        .map_err(|_| error("invalid number"))
+}
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Value, Error> {
+    skip_whitespace(chars);
+    if depth > MAX_DEPTH && matches!(chars.peek(), Some('[' | '{')) {
+        return Err(error("too deeply nested"));
+    }
+    match chars.peek() {
+        Some('n') => expect_word(chars, "null", Value::Null),
+        Some('t') => expect_word(chars, "true", Value::Bool(true)),
+        Some('f') => expect_word(chars, "false", Value::Bool(false)),
+        Some('"') => {
+            chars.next();
+            Ok(Value::String(parse_string(chars)?))
+        }
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Ok(Value::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Value::Array(items)),
+                    _ => return Err(error("expected , or ]")),
+                }
+            }
+        }
+        Some('{') => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Ok(Value::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                if chars.next() != Some('"') {
+                    return Err(error("expected a key"));
+                }
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(error("expected :"));
+                }
+                members.push((key, parse_value(chars, depth + 1)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Value::Object(members)),
+                    _ => return Err(error("expected , or }")),
+                }
+            }
+        }
+        Some(c) if *c == '-' || c.is_ascii_digit() => parse_number(chars),
+        _ => Err(error("unexpected char")),
+    }
+}
+
+impl Value {
+    pub fn parse(text: &str) -> Result<Value, Error> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars, 1)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(_) => Err(error("trailing chars")),
+        }
+    }
+
+    pub fn object(members: Vec<(&str, Value)>) -> Value {
+        Value::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(text: &str) -> Value {
+        Value::String(text.to_string())
+    }
+
+    // Member of an object, Null when missing.
+    pub fn get(&self, key: &str) -> &Value {
+        match self {
+            Value::Object(members) => members
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&Value::Null, |(_, value)| value),
+            _ => &Value::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            }
+            Value::Number(number) => write!(f, "{}", number),
+            Value::String(text) => write_string(f, text),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+pub fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+pub fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+// First char of a rule line, 0 and 1 being the FALSE and TRUE constants.
+pub fn is_rule_start(c: char) -> bool {
+    is_name_start(c) || matches!(c, '(' | '!' | '[' | '0' | '1')
+}
+
+pub fn read_name(first: char, chars: &mut Peekable<Chars>) -> String {
+    let mut name = first.to_string();
+    while let Some(&c) = chars.peek() {
+        if !is_name_char(c) {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    name
+}
+
+// A word made of uppercase letters only is a run of single letter facts
+// ("ABG" is A, B and G), any other word is one named fact.
+pub fn split_name(name: &str) -> Vec<String> {
+    if name.chars().all(|c| c.is_ascii_uppercase()) {
+        name.chars().map(|c| c.to_string()).collect()
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+pub fn is_cardinality(name: &str) -> bool {
+    matches!(name, "atleast" | "atmost" | "exactly")
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if is_name_start(c) => (),
+        _ => return false,
+    }
+    !name.ends_with('.') && !name.contains("..") && chars.all(is_name_char)
+}
+
+pub fn names(text: &str) -> Vec<String> {
+    let text = match text.find('#') {
+        Some(comment) => &text[..comment],
+        None => text,
+    };
+    let mut names = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_name_start(c) {
+            let name = read_name(c, &mut chars);
+            if !is_cardinality(&name) || chars.peek() != Some(&'(') {
+                names.append(&mut split_name(&name));
+            }
+        }
+    }
+    names
+}
+
+// A query holding anything but fact names, "?(A + !B) | C", is one
+// expression to evaluate rather than a list of facts.
+pub fn is_expression(query: &str) -> bool {
+    let query = query.trim_start();
+    let query = query.strip_prefix('?').unwrap_or(query);
+    strip_comment(query)
+        .chars()
+        .any(|c| !is_name_char(c) && !c.is_whitespace())
+}
+
+pub fn keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let text = text.trim();
+    if !text.starts_with(keyword) {
+        return None;
+    }
+    let rest = &text[keyword.len()..];
+    if !rest.starts_with(char::is_whitespace) && !rest.starts_with('"') {
+        return None;
+    }
+    let rest = match rest.find('#') {
+        Some(comment) => &rest[..comment],
+        None => rest,
+    };
+    Some(rest.trim())
+}
+
+// "name: description" declares a fact, returns the name and the description.
+pub fn declaration(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim();
+    if !text.starts_with(is_name_start) {
+        return None;
+    }
+    let end = text.find(|c| !is_name_char(c)).unwrap_or(text.len());
+    let rest = text[end..].trim_start().strip_prefix(':')?;
+    let rest = match rest.find('#') {
+        Some(comment) => &rest[..comment],
+        None => rest,
+    };
+    Some((&text[..end], rest.trim()))
+}
+
+// Error raised at a given byte offset of the parsed text, so that the
+// reported location can point at the faulty line and column.
+#[derive(Debug)]
+pub struct Positioned {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Positioned {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Positioned {}
+
+pub fn positioned(error: std::io::Error, offset: usize) -> std::io::Error {
+    let message = error.to_string();
+    std::io::Error::new(error.kind(), Positioned { offset, message })
+}
+
+pub fn strip_comment(text: &str) -> &str {
+    match text.find('#') {
+        Some(comment) => &text[..comment],
+        None => text,
+    }
+}
+
+// A rule goes on over the next line while a parenthesis is left open or
+// when it ends with an operator.
+pub fn continues(text: &str) -> bool {
+    let text = strip_comment(text).trim_end();
+    let depth = text.chars().fold(0, |depth, c| match c {
+        '(' => depth + 1,
+        ')' => depth - 1,
+        _ => depth,
+    });
+    depth > 0 || text.ends_with(['+', '|', '^', '!', '(', '>'])
+}
+
+// A blank line, initial facts, queries or a scenario end a pending rule.
+pub fn continuation(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty()
+        && !text.starts_with(['?', '@'])
+        && (!text.starts_with('=') || text.starts_with("=>"))
+}
+
+fn starts_rule(text: &str) -> bool {
+    text.trim_start().starts_with(is_rule_start)
+        && declaration(text).is_none()
+        && !["include", "module", "export"]
+            .iter()
+            .any(|word| keyword(text, word).is_some())
+}
+
+// Ranges of the physical lines making each logical line, a rule left open
+// going on over the continuation lines after it.
+pub fn logical_lines<S: AsRef<str>>(lines: &[S]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start + 1;
+        if starts_rule(lines[start].as_ref()) {
+            let mut code = strip_comment(lines[start].as_ref()).to_string();
+            while continues(&code) && end < lines.len() && continuation(lines[end].as_ref()) {
+                code.push('\n');
+                code.push_str(strip_comment(lines[end].as_ref()));
+                end += 1;
+            }
+        }
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
+
+// Text of a logical line, one physical line per '\n'. The comments of all
+// but its last line are left out of it and returned apart.
+pub fn join<S: AsRef<str>>(lines: &[S]) -> (String, Vec<&str>) {
+    let mut text = String::new();
+    let mut comments = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.as_ref().trim_end_matches('\n');
+        if i + 1 == lines.len() {
+            text.push_str(line);
+            break;
+        }
+        let code = strip_comment(line);
+        if code.len() < line.len() {
+            comments.push(line[code.len()..].trim_end());
+        }
+        text.push_str(code);
+        text.push('\n');
+    }
+    (text, comments)
+}
+
+// Trailing certainty factor of a rule, "A => B [0.8]": the offset of its
+// '[' and the text between the brackets.
+pub fn certainty(line: &str) -> Option<(usize, &str)> {
+    let code = strip_comment(line).trim_end();
+    let inner = code.strip_suffix(']')?;
+    let open = inner.rfind('[')?;
+    Some((open, &inner[open + 1..]))
+}
+
+const COMPARISONS: [&str; 6] = [">=", "<=", "==", "!=", ">", "<"];
+
+// Length of the number starting text, "-39.2".
+fn number_len(text: &str) -> usize {
+    let sign = usize::from(text.starts_with('-'));
+    sign + text[sign..]
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len() - sign)
+}
+
+// Comparison following the name of a numeric fact, "temperature > 38": the
+// operator, the number and the length of the text read.
+pub fn comparison(rest: &str) -> Option<(&'static str, f64, usize)> {
+    let text = rest.trim_start();
+    let operator = COMPARISONS.iter().find(|op| text.starts_with(**op))?;
+    let after = &text[operator.len()..];
+    // "<=>" is an equivalence.
+    if after.starts_with('>') {
+        return None;
+    }
+    let number = after.trim_start();
+    let len = number_len(number);
+    let threshold = number[..len].parse().ok()?;
+    Some((operator, threshold, rest.len() - number.len() + len))
+}
+
+// Numeric facts compared in a rule or a query, as (name, operator, number).
+pub fn comparisons(text: &str) -> Vec<(String, &'static str, f64)> {
+    let text = strip_comment(text);
+    let mut found = Vec::new();
+    let mut at = 0;
+    while let Some(start) = text[at..].find(is_name_start) {
+        let start = at + start;
+        let end = text[start..]
+            .find(|c| !is_name_char(c))
+            .map_or(text.len(), |end| start + end);
+        if let Some((operator, threshold, _)) = comparison(&text[end..]) {
+            found.push((text[start..end].to_string(), operator, threshold));
+        }
+        at = end;
+    }
+    found
+}
+
+// Value given to a numeric fact on the initial facts line, "temperature = 39.2":
+// the text of the number and the length of the text read.
+pub fn value(rest: &str) -> Option<(&str, usize)> {
+    let text = rest.trim_start();
+    let number = text.strip_prefix('=')?.trim_start();
+    // Anything else than a number is read up to the next word, to be reported.
+    let len = match number_len(number) {
+        0 => number.find(char::is_whitespace).unwrap_or(number.len()),
+        len => len,
+    };
+    Some((&number[..len], rest.len() - number.len() + len))
+}
@@ -1,9 +1,28 @@
+pub mod certainty;
 pub mod checker;
+pub mod circuit;
+pub mod diff;
+pub mod dot;
 pub mod expert_system;
+pub mod explain;
 pub mod facts;
+pub mod format;
+pub mod fuzzy;
 pub mod graph;
+pub mod json;
+pub mod lexer;
+pub mod lsp;
 pub mod options;
 pub mod print;
+pub mod probability;
+pub mod regression;
 pub mod rules;
+pub mod scenario;
+pub mod serve;
+pub mod session;
 pub mod solver;
+pub mod source;
+pub mod syntax;
 pub mod tree_builder;
+pub mod watch;
+pub mod weighted;
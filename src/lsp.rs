@@ -0,0 +1,202 @@
+pub mod analysis;
+
+use crate::json::Value;
+use analysis::Workspace;
+
+use std::io::{BufRead, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// Messages announcing a longer body are refused before reading it.
+const MAX_MESSAGE: usize = 1 << 24;
+
+fn read_message<R: BufRead>(input: &mut R) -> Result<Option<Value>, Error> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Lsp: missing Content-Length header"))?;
+    if length > MAX_MESSAGE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Lsp: message of {} bytes is too long", length),
+        ));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let body = String::from_utf8(body)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Lsp: message is not utf-8"))?;
+    Value::parse(&body).map(Some)
+}
+
+fn write_message<W: Write>(output: &mut W, message: Value) -> Result<(), Error> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn response(id: &Value, result: Result<Value, Error>) -> Value {
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err(error) => {
+            let code = match error.kind() {
+                ErrorKind::InvalidInput => -32602.0,
+                ErrorKind::Unsupported => -32601.0,
+                _ => -32603.0,
+            };
+            (
+                "error",
+                Value::object(vec![
+                    ("code", Value::Number(code)),
+                    ("message", Value::String(error.to_string())),
+                ]),
+            )
+        }
+    };
+    Value::object(vec![
+        ("jsonrpc", Value::string("2.0")),
+        ("id", id.clone()),
+        outcome,
+    ])
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    Value::object(vec![
+        ("jsonrpc", Value::string("2.0")),
+        ("method", Value::string(method)),
+        ("params", params),
+    ])
+}
+
+fn capabilities() -> Value {
+    Value::object(vec![
+        (
+            "capabilities",
+            Value::object(vec![
+                ("textDocumentSync", Value::Number(1.0)),
+                ("hoverProvider", Value::Bool(true)),
+                ("definitionProvider", Value::Bool(true)),
+                ("referencesProvider", Value::Bool(true)),
+                ("renameProvider", Value::Bool(true)),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Value::object(vec![("name", Value::string("expert_system"))]),
+        ),
+    ])
+}
+
+fn document_path(params: &Value) -> Result<PathBuf, Error> {
+    let uri = params.get("textDocument").get("uri").as_str();
+    uri.and_then(analysis::uri_to_path)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Lsp: expected a file uri"))
+}
+
+fn diagnostics(path: &Path, diagnostics: Vec<Value>) -> Value {
+    notification(
+        "textDocument/publishDiagnostics",
+        Value::object(vec![
+            ("uri", Value::String(analysis::path_to_uri(path))),
+            ("diagnostics", Value::Array(diagnostics)),
+        ]),
+    )
+}
+
+// Every open document is checked again, as it may include the changed one.
+fn publish_all<W: Write>(workspace: &Workspace, output: &mut W) -> Result<(), Error> {
+    let mut paths: Vec<&PathBuf> = workspace.documents.keys().collect();
+    paths.sort();
+    for path in paths {
+        let found = workspace.diagnostics(path);
+        write_message(output, diagnostics(path, found))?;
+    }
+    Ok(())
+}
+
+fn notify<W: Write>(
+    workspace: &mut Workspace,
+    method: &str,
+    params: &Value,
+    output: &mut W,
+) -> Result<(), Error> {
+    match method {
+        "textDocument/didOpen" => {
+            let text = params.get("textDocument").get("text").as_str();
+            workspace.open(&document_path(params)?, text.unwrap_or("").to_string());
+            publish_all(workspace, output)
+        }
+        "textDocument/didChange" => {
+            // Full synchronization, the last change holds the whole text.
+            let changes = params.get("contentChanges").as_array();
+            let text = changes
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text").as_str());
+            if let Some(text) = text {
+                workspace.open(&document_path(params)?, text.to_string());
+            }
+            publish_all(workspace, output)
+        }
+        "textDocument/didClose" => {
+            let path = document_path(params)?;
+            workspace.close(&path);
+            write_message(output, diagnostics(&path, Vec::new()))?;
+            publish_all(workspace, output)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn request(workspace: &Workspace, method: &str, params: &Value) -> Result<Value, Error> {
+    let position = params.get("position");
+    match method {
+        "initialize" => Ok(capabilities()),
+        "shutdown" => Ok(Value::Null),
+        "textDocument/hover" => workspace.hover(&document_path(params)?, position),
+        "textDocument/definition" => workspace.definition(&document_path(params)?, position),
+        "textDocument/references" => workspace.references(&document_path(params)?, position),
+        "textDocument/rename" => {
+            let new_name = params.get("newName").as_str().unwrap_or("");
+            workspace.rename(&document_path(params)?, position, new_name)
+        }
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Lsp: unknown method {}", method),
+        )),
+    }
+}
+
+// Serves the Language Server Protocol until the exit notification or the
+// end of the input. Nothing but protocol messages is written to output.
+pub fn run<R: BufRead, W: Write>(mut input: R, mut output: W) -> Result<(), Error> {
+    let mut workspace = Workspace::default();
+    while let Some(message) = read_message(&mut input)? {
+        let method = message.get("method").as_str().unwrap_or("").to_string();
+        let params = message.get("params");
+        let id = message.get("id");
+        if method == "exit" {
+            break;
+        }
+        if id.is_null() {
+            if let Err(error) = notify(&mut workspace, &method, params, &mut output) {
+                eprintln!("lsp: {}: {}", method, error);
+            }
+        } else {
+            let result = request(&workspace, &method, params);
+            write_message(&mut output, response(id, result))?;
+        }
+    }
+    Ok(())
+}
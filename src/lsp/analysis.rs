@@ -0,0 +1,500 @@
+use crate::expert_system;
+use crate::facts::{Facts, State};
+use crate::json::Value;
+use crate::lexer;
+use crate::options::Options;
+use crate::source::Source;
+use crate::syntax::{self, tokens::TokenKind, NodeKind, Span, SyntaxElement, SyntaxNode};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// A fact written in a file. Letters of an uppercase run ("=ABC") are
+// occurrences of their own.
+pub struct Occurrence {
+    pub span: Span,
+    pub written: String,
+    // Whether it is written after the impliance of a rule.
+    pub conclusion: bool,
+    pub run_start: bool,
+    pub run_end: bool,
+}
+
+fn push_name(token: &syntax::tokens::SyntaxToken, conclusion: bool, out: &mut Vec<Occurrence>) {
+    let names = lexer::split_name(&token.text);
+    for (i, name) in names.iter().enumerate() {
+        let start = if names.len() > 1 {
+            token.span.start + i
+        } else {
+            token.span.start
+        };
+        out.push(Occurrence {
+            span: Span {
+                start,
+                end: start + name.len(),
+            },
+            written: name.clone(),
+            conclusion,
+            run_start: i == 0,
+            run_end: i + 1 == names.len(),
+        });
+    }
+}
+
+fn rule_occurrences(node: &SyntaxNode, out: &mut Vec<Occurrence>) {
+    let mut conclusion = false;
+    for child in node.children.iter() {
+        match child {
+            SyntaxElement::Node(label) if label.kind == NodeKind::Label => (),
+            SyntaxElement::Node(expression) => {
+                for token in expression.tokens() {
+                    if token.kind == TokenKind::Name {
+                        push_name(token, conclusion, out);
+                    }
+                }
+            }
+            SyntaxElement::Token(token) => match token.kind {
+                TokenKind::Imply | TokenKind::Equiv => conclusion = true,
+                TokenKind::Name => push_name(token, conclusion, out),
+                _ => (),
+            },
+        }
+    }
+}
+
+// Facts written on a line of the syntax tree.
+pub fn occurrences(line: &SyntaxNode) -> Vec<Occurrence> {
+    let mut out = Vec::new();
+    let names = line
+        .tokens()
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Name);
+    match line.kind {
+        NodeKind::Rule => rule_occurrences(line, &mut out),
+        NodeKind::InitialFacts | NodeKind::Queries => {
+            names.for_each(|token| push_name(token, false, &mut out))
+        }
+        NodeKind::Expectations => names
+            .filter(|token| State::from_word(&token.text).is_none())
+            .for_each(|token| push_name(token, false, &mut out)),
+        NodeKind::Declaration => names
+            .take(1)
+            .for_each(|token| push_name(token, false, &mut out)),
+        NodeKind::Directive if line.to_string().trim_start().starts_with("export") => {
+            names.for_each(|token| push_name(token, false, &mut out))
+        }
+        _ => (),
+    }
+    out
+}
+
+// Offsets are bytes, LSP positions count UTF-16 units on each line.
+pub fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Value::object(vec![
+        ("line", Value::Number(line as f64)),
+        ("character", Value::Number(character as f64)),
+    ])
+}
+
+pub fn offset(text: &str, position: &Value) -> usize {
+    let line = position.get("line").as_usize().unwrap_or(0);
+    let character = position.get("character").as_usize().unwrap_or(0);
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(newline) => start += newline + 1,
+            None => return text.len(),
+        }
+    }
+    let mut units = 0;
+    for (i, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+pub fn range(text: &str, span: Span) -> Value {
+    Value::object(vec![
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+fn hex(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|digit| digit as u8)
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::new();
+    let mut i = 0;
+    while i < encoded.len() {
+        match (encoded[i], encoded.get(i + 1), encoded.get(i + 2)) {
+            (b'%', Some(&high), Some(&low)) if hex(high).is_some() && hex(low).is_some() => {
+                bytes.push(hex(high)? * 16 + hex(low)?);
+                i += 3;
+            }
+            (byte, _, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Splits "file:line[:column]: message" as built by source::Line::error.
+fn located(message: &str) -> Option<(&str, usize, usize, &str)> {
+    let (location, rest) = message.split_once(": ")?;
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next()?.parse::<usize>().ok()?;
+    let middle = parts.next()?;
+    match (middle.parse::<usize>(), parts.next()) {
+        (Ok(line), Some(file)) => Some((file, line, last, rest)),
+        _ => Some((location.rsplit_once(':')?.0, last, 1, rest)),
+    }
+}
+
+fn diagnostic(text: &str, span: Span, message: &str) -> Value {
+    Value::object(vec![
+        ("range", range(text, span)),
+        ("severity", Value::Number(1.0)),
+        ("source", Value::string("expert_system")),
+        ("message", Value::string(message)),
+    ])
+}
+
+// Byte offset of a "line:column" position, both counted from 1.
+fn line_column(text: &str, line: usize, column: usize) -> usize {
+    let position = Value::object(vec![
+        ("line", Value::Number(line.saturating_sub(1) as f64)),
+        ("character", Value::Number(column.saturating_sub(1) as f64)),
+    ]);
+    offset(text, &position)
+}
+
+// Open documents of the editor, by canonical path.
+#[derive(Default)]
+pub struct Workspace {
+    pub documents: HashMap<PathBuf, String>,
+}
+
+impl Workspace {
+    pub fn open(&mut self, path: &Path, text: String) {
+        self.documents.insert(canonical(path), text);
+    }
+
+    pub fn close(&mut self, path: &Path) {
+        self.documents.remove(&canonical(path));
+    }
+
+    pub fn text(&self, path: &Path) -> Option<String> {
+        match self.documents.get(&canonical(path)) {
+            Some(text) => Some(text.clone()),
+            None => fs::read_to_string(path).ok(),
+        }
+    }
+
+    fn load(&self, root: &Path) -> Result<Source, Error> {
+        Source::load_overlaid(root, self.documents.clone())
+    }
+
+    // Syntax errors of the document, then the first error of the parser
+    // and checker once the syntax is right.
+    pub fn diagnostics(&self, path: &Path) -> Vec<Value> {
+        let text = self.text(path).unwrap_or_default();
+        let tree = syntax::parse(&text);
+        let mut diagnostics: Vec<Value> = tree
+            .errors
+            .iter()
+            .map(|error| diagnostic(&text, error.span, &error.message))
+            .collect();
+        if !diagnostics.is_empty() {
+            return diagnostics;
+        }
+        let checked = self.load(path).and_then(|source| {
+            let facts = Facts::from_source(&source);
+            expert_system::rules_of_source(&source, &facts, &Options::new()).map(|_| source)
+        });
+        if let Err(error) = checked {
+            let message = error.to_string();
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+            let span = match located(&message) {
+                Some((file, line, column, _)) if Some(file.to_string()) == name => {
+                    let at = line_column(&text, line, column);
+                    let end = text[at..].find('\n').map_or(text.len(), |n| at + n);
+                    Span { start: at, end }
+                }
+                _ => Span::default(),
+            };
+            diagnostics.push(diagnostic(&text, span, &message));
+        }
+        diagnostics
+    }
+
+    // Module of each file of a source, by canonical path.
+    fn files(&self, source: &Source) -> Vec<(PathBuf, Option<String>)> {
+        source
+            .files
+            .iter()
+            .map(|path| {
+                let name = source.display_name(path);
+                let module = source
+                    .lines
+                    .iter()
+                    .find(|line| *line.file == name)
+                    .and_then(|line| line.module.as_ref().map(|m| m.to_string()));
+                (path.clone(), module)
+            })
+            .collect()
+    }
+
+    // Facts written in a file with the full name they resolve to.
+    fn resolved(
+        &self,
+        facts: &Facts,
+        path: &Path,
+        module: Option<String>,
+    ) -> Vec<(Occurrence, String)> {
+        let text = self.text(path).unwrap_or_default();
+        facts.set_scope(module.map(std::rc::Rc::new));
+        let mut resolved = Vec::new();
+        for line in syntax::parse(&text).root.nodes() {
+            for occurrence in occurrences(line) {
+                if let Ok(fact) = facts.lookup(&occurrence.written) {
+                    let name = fact.name.clone();
+                    resolved.push((occurrence, name));
+                }
+            }
+        }
+        facts.set_scope(None);
+        resolved
+    }
+
+    // Full name of the fact written at the offset of a document.
+    fn fact_at(&self, source: &Source, facts: &Facts, path: &Path, at: usize) -> Option<String> {
+        let path = canonical(path);
+        let module = self
+            .files(source)
+            .into_iter()
+            .find(|(file, _)| *file == path)
+            .and_then(|(_, module)| module);
+        self.resolved(facts, &path, module)
+            .into_iter()
+            .find(|(occurrence, _)| occurrence.span.start <= at && at <= occurrence.span.end)
+            .map(|(_, name)| name)
+    }
+
+    pub fn hover(&self, path: &Path, position: &Value) -> Result<Value, Error> {
+        let text = self.text(path).unwrap_or_default();
+        let source = self.load(path)?;
+        let facts = Facts::from_source(&source);
+        let name = match self.fact_at(&source, &facts, path, offset(&text, position)) {
+            Some(name) => name,
+            None => return Ok(Value::Null),
+        };
+        let fact = facts.get(&name).unwrap();
+        let mut value = format!("**{}**", fact.name);
+        if let Some(description) = &fact.description {
+            value.push_str(&format!(": {}", description));
+        }
+        match expert_system::rules_of_source(&source, &facts, &Options::new()) {
+            Ok(rules) => {
+                let concluding: Vec<String> = rules
+                    .iter()
+                    .filter(|rule| rule.implies_fact(fact))
+                    .map(|rule| format!("- {}: `{}`", rule.provenance(), rule.to_infix()))
+                    .collect();
+                if concluding.is_empty() {
+                    value.push_str("\n\nNo rule concludes it, it is an initial fact or false.");
+                } else {
+                    value.push_str("\n\nDerived by:\n");
+                    value.push_str(&concluding.join("\n"));
+                }
+            }
+            Err(error) => value.push_str(&format!("\n\n{}", error)),
+        }
+        Ok(Value::object(vec![(
+            "contents",
+            Value::object(vec![
+                ("kind", Value::string("markdown")),
+                ("value", Value::String(value)),
+            ]),
+        )]))
+    }
+
+    // Rules concluding the fact under the cursor.
+    pub fn definition(&self, path: &Path, position: &Value) -> Result<Value, Error> {
+        let text = self.text(path).unwrap_or_default();
+        let source = self.load(path)?;
+        let facts = Facts::from_source(&source);
+        let name = match self.fact_at(&source, &facts, path, offset(&text, position)) {
+            Some(name) => name,
+            None => return Ok(Value::Null),
+        };
+        let rules = expert_system::rules_of_source(&source, &facts, &Options::new())?;
+        let fact = facts.get(&name).unwrap();
+        let mut locations = Vec::new();
+        for rule in rules.iter().filter(|rule| rule.implies_fact(fact)) {
+            let (file, line) = match rule.location.as_ref().and_then(|l| l.rsplit_once(':')) {
+                Some((file, line)) => (source.path_of(file), line.parse::<usize>().unwrap_or(1)),
+                None => continue,
+            };
+            let file_text = self.text(&file).unwrap_or_default();
+            let at = line_column(&file_text, line, 1);
+            let module = self
+                .files(&source)
+                .into_iter()
+                .find(|(path, _)| *path == canonical(&file))
+                .and_then(|(_, module)| module);
+            let end = file_text[at..]
+                .find('\n')
+                .map_or(file_text.len(), |n| at + n);
+            let mut span = Span { start: at, end };
+            let in_rule: Vec<Occurrence> = self
+                .resolved(&facts, &file, module)
+                .into_iter()
+                .filter(|(occurrence, written)| *written == name && occurrence.span.start >= at)
+                .map(|(occurrence, _)| occurrence)
+                .collect();
+            if let Some(occurrence) = in_rule
+                .iter()
+                .find(|occurrence| occurrence.conclusion)
+                .or_else(|| in_rule.first())
+            {
+                span = occurrence.span;
+            }
+            let location = Value::object(vec![
+                ("uri", Value::String(path_to_uri(&file))),
+                ("range", range(&file_text, span)),
+            ]);
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+        Ok(Value::Array(locations))
+    }
+
+    // Occurrences of the fact under the cursor in every file reachable from
+    // the open documents.
+    fn references_of(
+        &self,
+        path: &Path,
+        position: &Value,
+    ) -> Result<BTreeMap<PathBuf, Vec<Occurrence>>, Error> {
+        let text = self.text(path).unwrap_or_default();
+        let source = self.load(path)?;
+        let facts = Facts::from_source(&source);
+        let name = match self.fact_at(&source, &facts, path, offset(&text, position)) {
+            Some(name) => name,
+            None => return Ok(BTreeMap::new()),
+        };
+        let mut roots: Vec<PathBuf> = vec![canonical(path)];
+        roots.extend(self.documents.keys().cloned());
+        let mut found: BTreeMap<PathBuf, Vec<Occurrence>> = BTreeMap::new();
+        for root in roots.iter() {
+            let source = match self.load(root) {
+                Ok(source) => source,
+                Err(_) => continue,
+            };
+            let facts = Facts::from_source(&source);
+            for (file, module) in self.files(&source) {
+                if found.contains_key(&file) {
+                    continue;
+                }
+                let occurrences = self
+                    .resolved(&facts, &file, module)
+                    .into_iter()
+                    .filter(|(_, written)| *written == name)
+                    .map(|(occurrence, _)| occurrence)
+                    .collect();
+                found.insert(file, occurrences);
+            }
+        }
+        Ok(found)
+    }
+
+    pub fn references(&self, path: &Path, position: &Value) -> Result<Value, Error> {
+        let mut locations = Vec::new();
+        for (file, occurrences) in self.references_of(path, position)? {
+            let text = self.text(&file).unwrap_or_default();
+            for occurrence in occurrences {
+                locations.push(Value::object(vec![
+                    ("uri", Value::String(path_to_uri(&file))),
+                    ("range", range(&text, occurrence.span)),
+                ]));
+            }
+        }
+        Ok(Value::Array(locations))
+    }
+
+    // Renames a fact in every file reachable from the open documents,
+    // qualified names keep their module.
+    pub fn rename(&self, path: &Path, position: &Value, new_name: &str) -> Result<Value, Error> {
+        if !lexer::is_valid_name(new_name)
+            || new_name.contains('.')
+            || lexer::split_name(new_name).len() != 1
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Rename: invalid fact name ({})", new_name),
+            ));
+        }
+        let mut changes = Vec::new();
+        for (file, occurrences) in self.references_of(path, position)? {
+            if occurrences.is_empty() {
+                continue;
+            }
+            let text = self.text(&file).unwrap_or_default();
+            let edits = occurrences
+                .iter()
+                .map(|occurrence| {
+                    let mut span = occurrence.span;
+                    if let Some(dot) = occurrence.written.rfind('.') {
+                        span.start += dot + 1;
+                    }
+                    // A letter taken out of a run gets spaces around it.
+                    let mut new_text = new_name.to_string();
+                    if !occurrence.run_start {
+                        new_text.insert(0, ' ');
+                    }
+                    if !occurrence.run_end {
+                        new_text.push(' ');
+                    }
+                    Value::object(vec![
+                        ("range", range(&text, span)),
+                        ("newText", Value::String(new_text)),
+                    ])
+                })
+                .collect();
+            changes.push((path_to_uri(&file), Value::Array(edits)));
+        }
+        Ok(Value::object(vec![("changes", Value::Object(changes))]))
+    }
+}
@@ -1,3 +1,8 @@
+use crate::fuzzy::Norm;
+
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
 #[derive(Default)]
 pub struct Options {
     pub graph: bool,
@@ -5,6 +10,19 @@ pub struct Options {
     pub file: bool,
     pub comment: bool,
     pub log: bool,
+    // Directory the dot files are written to, exported if set.
+    pub dot: Option<String>,
+    pub open_world: bool,
+    pub explain: u8,
+    pub template: Option<String>,
+    pub check: bool,
+    pub watch: bool,
+    pub port: Option<u16>,
+    pub certainty: bool,
+    pub fuzzy: Option<Norm>,
+    pub probability: bool,
+    // Number of worlds sampled with --probability, exact counting if None.
+    pub samples: Option<usize>,
 }
 
 impl Options {
@@ -15,19 +33,110 @@ impl Options {
             file: false,
             comment: false,
             log: false,
+            dot: None,
+            open_world: false,
+            explain: 0,
+            template: None,
+            check: false,
+            watch: false,
+            port: None,
+            certainty: false,
+            fuzzy: None,
+            probability: false,
+            samples: None,
+        }
+    }
+
+    // Facts are valued by a single mode, --samples being one of --probability.
+    fn exclusive(&self, name: &str) -> Result<(), Error> {
+        let modes = [
+            ("--certainty", self.certainty),
+            ("--fuzzy", self.fuzzy.is_some()),
+            ("--probability", self.probability),
+        ];
+        let name = if name == "--samples" {
+            "--probability"
+        } else {
+            name
+        };
+        match modes.iter().find(|(mode, set)| *set && *mode != name) {
+            Some((mode, _)) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Options: {} conflicts with {}", name, mode),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // Long options are matched whole, "--name" or "--name=value".
+    fn load_long(&mut self, option: &str) -> Result<(), Error> {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (option, None),
+        };
+        if MODES.contains(&name) {
+            self.exclusive(name)?;
         }
+        match (name, value) {
+            ("--template", Some(path)) => self.template = Some(path.to_string()),
+            ("--dot", Some(dir)) => self.dot = Some(dir.to_string()),
+            ("--port", Some(port)) => self.port = Some(number(option, port)?),
+            ("--check", None) => self.check = true,
+            ("--watch", None) => self.watch = true,
+            ("--fuzzy", norm) => self.fuzzy = Some(Norm::from_name(norm.unwrap_or_default())?),
+            ("--probability", None) => self.probability = true,
+            ("--samples", Some(samples)) => {
+                self.probability = true;
+                self.samples = Some(number(option, samples)?);
+            }
+            ("--certainty", None) => self.certainty = true,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Options: unknown option {}", option),
+                ))
+            }
+        }
+        Ok(())
     }
 
-    pub fn load(&mut self, options: &str) {
-        for c in options.chars() {
+    pub fn load(&mut self, options: &str) -> Result<(), Error> {
+        if options.starts_with("--") {
+            return self.load_long(options);
+        }
+        for c in options.chars().skip(1) {
             match c {
                 'g' => self.graph = true,
                 'i' => self.interactive = true,
                 'f' => self.file = true,
                 'c' => self.comment = true,
                 'l' => self.log = true,
-                _ => continue,
+                'd' => {
+                    self.dot.get_or_insert_with(|| ".".to_string());
+                }
+                'o' => self.open_world = true,
+                'e' => self.explain = (self.explain + 1).min(3),
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Options: unknown option -{} (in {})", c, options),
+                    ))
+                }
             }
         }
+        Ok(())
+    }
+}
+
+const MODES: [&str; 4] = ["--certainty", "--fuzzy", "--probability", "--samples"];
+
+// Value of a numeric option, which must be positive.
+fn number<T: FromStr + PartialEq + Default>(option: &str, value: &str) -> Result<T, Error> {
+    match value.parse() {
+        Ok(number) if number != T::default() => Ok(number),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Options: invalid number ({})", option),
+        )),
     }
 }
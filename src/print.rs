@@ -1,35 +1,108 @@
-use crate::facts::Fact;
+use crate::facts::{Fact, Measure, State};
 use crate::graph::{Graph, NodeIndex};
 use crate::rules::rule::token::Token;
+use crate::scenario::ScenarioResult;
 
 use std::fs::File;
 use std::io::{prelude::*, Error};
 
-pub fn results(solved_queries: &[Fact]) {
+fn measure_to_string(measure: Option<Measure>) -> String {
+    match measure {
+        Some(Measure::Certainty(certainty)) => format!(" (CF {:.2})", certainty),
+        Some(Measure::Degree(degree)) => format!(" (degree {:.2})", degree),
+        Some(Measure::Probability(probability)) => format!(" (P {:.2})", probability),
+        Some(Measure::Estimate(probability)) => format!(" (P ~{:.2})", probability),
+        None => String::new(),
+    }
+}
+
+pub fn results_to_string(solved_queries: &[Fact]) -> String {
+    let mut out = String::new();
     for fact in solved_queries.iter() {
-        print!("{}", fact.letter);
-        if fact.state.get() {
-            println!(" = True");
-        } else {
-            println!(" = False");
-        }
+        out.push_str(&format!(
+            "{} = {}{}\n",
+            fact.describe(),
+            fact.solved_state().as_str(),
+            measure_to_string(fact.measure.get())
+        ));
     }
+    out
 }
 
-pub fn solved_to_file(fname: &str, solved_queries: &[Fact]) -> Result<(), Error> {
-    let mut f = File::create(fname)?;
-    let mut fcontents = String::new();
+fn expressions_to_string(expressions: &[(String, State, Option<Measure>)]) -> String {
+    let mut out = String::new();
+    for (expression, state, measure) in expressions.iter() {
+        out.push_str(&format!(
+            "{} = {}{}\n",
+            expression,
+            state.as_str(),
+            measure_to_string(*measure)
+        ));
+    }
+    out
+}
+
+fn scenario_header(result: &ScenarioResult) -> String {
+    match &result.name {
+        Some(name) => format!("=== {} ===\n", name),
+        None => String::new(),
+    }
+}
+
+pub fn scenarios_to_string(results: &[ScenarioResult]) -> String {
+    let mut out = String::new();
+    for result in results.iter() {
+        out.push_str(&scenario_header(result));
+        out.push_str(&results_to_string(&result.solved_queries));
+        out.push_str(&expressions_to_string(&result.expressions));
+        out.push_str(&result.explanation);
+    }
+    out
+}
+
+pub fn expectations_to_string(results: &[ScenarioResult]) -> String {
+    let mut out = String::new();
+    for result in results.iter() {
+        out.push_str(&scenario_header(result));
+        out.push_str(&facts_expectations_to_string(&result.solved_queries));
+    }
+    out
+}
+
+fn facts_expectations_to_string(solved_queries: &[Fact]) -> String {
+    let mut out = String::new();
     for fact in solved_queries.iter() {
-        if fact.queried.get() {
-            fcontents.push(fact.letter);
-            if fact.state.get() {
-                fcontents.push_str(" = True\n");
-            } else {
-                fcontents.push_str(" = False\n");
-            }
+        if let Some(expected) = fact.expected.get() {
+            out.push_str(&format!("{} = {}\n", fact.describe(), expected.as_str()));
+        }
+    }
+    out
+}
+
+pub fn descriptions_to_string(facts: &[Fact]) -> String {
+    let mut out = String::new();
+    for fact in facts.iter() {
+        if let Some(description) = &fact.description {
+            out.push_str(&format!("{}: {}\n", fact.name, description));
         }
     }
-    f.write_all(fcontents.as_bytes())?;
+    out
+}
+
+pub fn results(results: &[ScenarioResult]) {
+    print!("{}", scenarios_to_string(results));
+}
+
+pub fn error_to_string(error: &Error) -> String {
+    format!(
+        "Oops, something went wrong, shutting program down.\nError: {:?}\n",
+        error.to_string()
+    )
+}
+
+pub fn solved_to_file(fname: &str, results: &[ScenarioResult]) -> Result<(), Error> {
+    let mut f = File::create(fname)?;
+    f.write_all(scenarios_to_string(results).as_bytes())?;
     Ok(())
 }
 
@@ -37,8 +110,12 @@ fn print_tree_rec(graph: &Graph<Token>, cur: NodeIndex, mut spaces: usize) {
     match graph.get(cur) {
         None => println!("Error: print_tree_rec() out of bounds."),
         Some(node) => {
-            let t_char = node.content.get_token_char();
-            print!("{} -> ", t_char);
+            if node.content.is_empty() {
+                // Root of an expression query.
+                print!("? -> ");
+            } else {
+                print!("{} -> ", node.content.get_token_str());
+            }
             if cur > 0 {
                 spaces += 5;
             }
@@ -49,7 +126,12 @@ fn print_tree_rec(graph: &Graph<Token>, cur: NodeIndex, mut spaces: usize) {
                     print_tree_rec(graph, rhs, spaces);
                 }
             } else if let Some(fact) = node.content.fact {
-                println!("{}", fact.state.get());
+                match &fact.description {
+                    Some(description) => println!("{} ({})", fact.state.get(), description),
+                    None => println!("{}", fact.state.get()),
+                }
+            } else if let Some(value) = node.content.constant {
+                println!("{}", value);
             } else {
                 println!("Error: last node isn't a fact.");
             }
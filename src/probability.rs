@@ -0,0 +1,484 @@
+use crate::circuit::{Circuit, FALSE, TRUE};
+use crate::facts::{Fact, Facts, State};
+use crate::options::Options;
+use crate::rules::{
+    rule::{
+        token::{Operand, Token},
+        Rule,
+    },
+    Rules,
+};
+use crate::solver;
+use crate::weighted::{self, Trace};
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
+
+pub const SAMPLES: usize = 10_000;
+
+// xorshift64*, seeded the same way on each run so that estimates are
+// reproducible.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let bits = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        bits as f64 / (1u64 << 53) as f64
+    }
+}
+
+// Worlds where a fact or an expression is surely true and where it is surely
+// false, neither holding where it is undetermined in an open world.
+#[derive(Clone, Copy)]
+struct Truth {
+    true_in: usize,
+    false_in: usize,
+}
+
+// Compiles the rules, read as the boolean solver reads them, into formulas of
+// the uncertain facts: a fact is decided by the first of its rules which
+// fires, and is false, or undetermined in an open world, when none does.
+struct Compiler<'a> {
+    rules: &'a Rules<'a>,
+    options: &'a Options,
+    circuit: Circuit,
+    variables: HashMap<String, usize>,
+    known: HashMap<String, Truth>,
+    solving: HashSet<String>,
+    // Whether a fact was met while solving itself, the solver then cutting
+    // the loop where it first meets it in each world.
+    looped: bool,
+}
+
+impl<'a> Compiler<'a> {
+    fn constant(value: bool) -> Truth {
+        Truth {
+            true_in: Circuit::constant(value),
+            false_in: Circuit::constant(!value),
+        }
+    }
+
+    fn not(truth: Truth) -> Truth {
+        Truth {
+            true_in: truth.false_in,
+            false_in: truth.true_in,
+        }
+    }
+
+    // Kleene three-valued logic, as in the solver.
+    fn compute(&mut self, operand: Operand, lhs: Truth, rhs: Truth) -> Truth {
+        let circuit = &mut self.circuit;
+        match operand {
+            Operand::And => Truth {
+                true_in: circuit.and(lhs.true_in, rhs.true_in),
+                false_in: circuit.or(lhs.false_in, rhs.false_in),
+            },
+            Operand::Or => Truth {
+                true_in: circuit.or(lhs.true_in, rhs.true_in),
+                false_in: circuit.and(lhs.false_in, rhs.false_in),
+            },
+            Operand::Xor => {
+                let differ = (
+                    circuit.and(lhs.true_in, rhs.false_in),
+                    circuit.and(lhs.false_in, rhs.true_in),
+                );
+                let same = (
+                    circuit.and(lhs.true_in, rhs.true_in),
+                    circuit.and(lhs.false_in, rhs.false_in),
+                );
+                Truth {
+                    true_in: circuit.or(differ.0, differ.1),
+                    false_in: circuit.or(same.0, same.1),
+                }
+            }
+            Operand::Nand => Compiler::not(self.compute(Operand::And, lhs, rhs)),
+            Operand::Nor => Compiler::not(self.compute(Operand::Or, lhs, rhs)),
+            Operand::Xnor => Compiler::not(self.compute(Operand::Xor, lhs, rhs)),
+            Operand::Imply => self.compute(Operand::Or, Compiler::not(lhs), rhs),
+            Operand::Equiv => self.compute(Operand::Xnor, lhs, rhs),
+            _ => Compiler::constant(false),
+        }
+    }
+
+    // A bound holds or fails once the undetermined values can't change the
+    // count enough.
+    fn cardinality(&mut self, operand: Operand, values: &[Truth]) -> Truth {
+        let true_in: Vec<usize> = values.iter().map(|value| value.true_in).collect();
+        let not_false_in: Vec<usize> = values
+            .iter()
+            .map(|value| self.circuit.not(value.false_in))
+            .collect();
+        let circuit = &mut self.circuit;
+        match operand {
+            Operand::AtLeast(k) => {
+                let fewer = circuit.at_least(k as usize, &not_false_in);
+                Truth {
+                    true_in: circuit.at_least(k as usize, &true_in),
+                    false_in: circuit.not(fewer),
+                }
+            }
+            Operand::AtMost(k) => {
+                let more = circuit.at_least(k as usize + 1, &not_false_in);
+                Truth {
+                    true_in: circuit.not(more),
+                    false_in: circuit.at_least(k as usize + 1, &true_in),
+                }
+            }
+            Operand::Exactly(k) => {
+                let at_least = self.cardinality(Operand::AtLeast(k), values);
+                let at_most = self.cardinality(Operand::AtMost(k), values);
+                self.compute(Operand::And, at_least, at_most)
+            }
+            _ => Compiler::constant(false),
+        }
+    }
+
+    // Values of the list of a cardinality, chained by commas.
+    fn values(&mut self, tokens: &[Token], i: &mut usize, values: &mut Vec<Truth>) {
+        if tokens.get(*i).and_then(|t| t.operand) == Some(Operand::Comma) {
+            *i += 1;
+            self.values(tokens, i, values);
+            self.values(tokens, i, values);
+        } else {
+            values.push(self.eval(tokens, i));
+        }
+    }
+
+    // Rules are stored in reversed RPN, an operator is followed by its right
+    // operand, then its left one.
+    fn eval(&mut self, tokens: &[Token], i: &mut usize) -> Truth {
+        let token = match tokens.get(*i) {
+            Some(token) => *token,
+            None => return Compiler::constant(false),
+        };
+        *i += 1;
+        if let Some(fact) = token.fact {
+            return self.fact(fact);
+        }
+        if let Some(constant) = token.constant {
+            return Compiler::constant(constant);
+        }
+        match token.operand {
+            Some(Operand::Not) => Compiler::not(self.eval(tokens, i)),
+            Some(op) if op.is_unary() => {
+                let mut values = Vec::new();
+                self.values(tokens, i, &mut values);
+                self.cardinality(op, &values)
+            }
+            Some(op) => {
+                let rhs = self.eval(tokens, i);
+                let lhs = self.eval(tokens, i);
+                self.compute(op, lhs, rhs)
+            }
+            None => Compiler::constant(false),
+        }
+    }
+
+    fn expression(&mut self, tokens: &[Token]) -> Truth {
+        self.eval(tokens, &mut 0)
+    }
+
+    fn fact(&mut self, fact: &Fact) -> Truth {
+        if let Some(variable) = self.variables.get(&fact.name) {
+            let true_in = self.circuit.variable(*variable);
+            return Truth {
+                true_in,
+                false_in: self.circuit.not(true_in),
+            };
+        }
+        if fact.determined.get() {
+            return Compiler::constant(fact.state.get());
+        }
+        if let Some(truth) = self.known.get(&fact.name) {
+            return *truth;
+        }
+        if !self.solving.insert(fact.name.clone()) {
+            self.looped = true;
+            return Compiler::constant(false);
+        }
+        let mut rules = Vec::new();
+        for rule in self.rules.iter() {
+            if rule.implies_fact(fact) {
+                let negated = fact.reverse_state.replace(false);
+                let premise = self.expression(&rule.lhs);
+                rules.push((premise.true_in, negated));
+            }
+        }
+        let mut truth = Truth {
+            true_in: FALSE,
+            false_in: Circuit::constant(!self.options.open_world),
+        };
+        for (fires, negated) in rules.into_iter().rev() {
+            let (if_true, if_false) = if negated {
+                (FALSE, TRUE)
+            } else {
+                (TRUE, FALSE)
+            };
+            truth = Truth {
+                true_in: self.circuit.ite(fires, if_true, truth.true_in),
+                false_in: self.circuit.ite(fires, if_false, truth.false_in),
+            };
+        }
+        self.solving.remove(&fact.name);
+        self.known.insert(fact.name.clone(), truth);
+        truth
+    }
+}
+
+// Probabilities of the queries, the initial facts given with a prior being
+// independent random facts. Each assignment of those facts is a world with a
+// single model under the rules, that of the boolean solver: the probability
+// of a query is the weighted count of the worlds where it is true. The rules
+// are compiled into a circuit of the uncertain facts, counted exactly, unless
+// they loop or the count takes too long, the worlds being then solved one by
+// one, all of them if there are no more than SAMPLES, else a sample.
+pub struct Probability<'a> {
+    facts: &'a Facts,
+    rules: &'a Rules<'a>,
+    options: &'a Options,
+    // Whether the last probabilities were estimated by sampling.
+    pub sampled: Cell<bool>,
+    pub trace: Trace,
+}
+
+impl<'a> Probability<'a> {
+    pub fn new(facts: &'a Facts, rules: &'a Rules<'a>, options: &'a Options) -> Self {
+        Probability {
+            facts,
+            rules,
+            options,
+            sampled: Cell::new(false),
+            trace: Trace::default(),
+        }
+    }
+
+    // Facts the queries depend on through the rules.
+    fn relevant(&self, mut pending: Vec<&'a Fact>) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        while let Some(fact) = pending.pop() {
+            if !seen.insert(fact.name.clone()) {
+                continue;
+            }
+            for rule in self.rules.iter() {
+                if rule
+                    .rhs
+                    .iter()
+                    .any(|t| t.fact.map(|f| &f.name) == Some(&fact.name))
+                {
+                    pending.extend(rule.lhs.iter().filter_map(|t| t.fact));
+                }
+            }
+        }
+        seen
+    }
+
+    // Exact probabilities from the circuit of the rules, None if they loop
+    // or counting takes too many steps.
+    fn count(
+        &self,
+        initial: &[Fact],
+        uncertain: &[(usize, f64)],
+        queries: &[&Fact],
+        expressions: &[Rule<'a>],
+    ) -> Option<Vec<f64>> {
+        self.facts.restore(initial);
+        let mut compiler = Compiler {
+            rules: self.rules,
+            options: self.options,
+            circuit: Circuit::new(uncertain.iter().map(|(_, prior)| *prior).collect()),
+            variables: uncertain
+                .iter()
+                .enumerate()
+                .map(|(variable, (index, _))| (initial[*index].name.clone(), variable))
+                .collect(),
+            known: HashMap::new(),
+            solving: HashSet::new(),
+            looped: false,
+        };
+        let mut formulas: Vec<usize> = queries
+            .iter()
+            .map(|fact| compiler.fact(fact).true_in)
+            .collect();
+        for expression in expressions.iter() {
+            formulas.push(compiler.expression(&expression.lhs).true_in);
+        }
+        if compiler.looped {
+            self.trace
+                .note("The rules loop, each world is solved apart.".to_string());
+            return None;
+        }
+        let mut probabilities = Vec::new();
+        for formula in formulas {
+            match compiler.circuit.probability(formula) {
+                Some(probability) => probabilities.push(probability),
+                None => {
+                    self.trace.note(format!(
+                        "Counting gave up after {} steps.",
+                        compiler.circuit.steps
+                    ));
+                    return None;
+                }
+            }
+        }
+        self.trace.note(format!(
+            "Counted exactly over {} uncertain facts, in {} steps.",
+            uncertain.len(),
+            compiler.circuit.steps
+        ));
+        Some(probabilities)
+    }
+
+    // Probabilities counted over every world.
+    fn enumerate(
+        &self,
+        initial: &[Fact],
+        uncertain: &[(usize, f64)],
+        queries: &[&Fact],
+        expressions: &[Rule<'a>],
+    ) -> Result<Vec<f64>, Error> {
+        let mut probabilities = vec![0.0; queries.len() + expressions.len()];
+        let worlds = 1usize << uncertain.len();
+        for world in 0..worlds {
+            let values: Vec<bool> = (0..uncertain.len())
+                .map(|bit| world & (1 << bit) != 0)
+                .collect();
+            let weight: f64 = uncertain
+                .iter()
+                .zip(values.iter())
+                .map(|((_, prior), value)| if *value { *prior } else { 1.0 - prior })
+                .product();
+            if weight == 0.0 {
+                continue;
+            }
+            let truths = self.world(initial, uncertain, &values, queries, expressions)?;
+            for (probability, truth) in probabilities.iter_mut().zip(truths) {
+                if truth {
+                    *probability += weight;
+                }
+            }
+        }
+        self.trace.note(format!(
+            "Counted over the {} worlds of {} uncertain facts.",
+            worlds,
+            uncertain.len()
+        ));
+        Ok(probabilities)
+    }
+
+    // Probabilities estimated over sampled worlds.
+    fn sample(
+        &self,
+        initial: &[Fact],
+        uncertain: &[(usize, f64)],
+        queries: &[&Fact],
+        expressions: &[Rule<'a>],
+    ) -> Result<Vec<f64>, Error> {
+        let mut probabilities = vec![0.0; queries.len() + expressions.len()];
+        let samples = self.options.samples.unwrap_or(SAMPLES).max(1);
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..samples {
+            let values: Vec<bool> = uncertain
+                .iter()
+                .map(|(_, prior)| random.next() < *prior)
+                .collect();
+            let truths = self.world(initial, uncertain, &values, queries, expressions)?;
+            for (probability, truth) in probabilities.iter_mut().zip(truths) {
+                if truth {
+                    *probability += 1.0 / samples as f64;
+                }
+            }
+        }
+        self.trace.note(format!(
+            "Estimated over {} sampled worlds of {} uncertain facts.",
+            samples,
+            uncertain.len()
+        ));
+        Ok(probabilities)
+    }
+
+    // Truth of each query, then each expression, in a world.
+    fn world(
+        &self,
+        initial: &[Fact],
+        uncertain: &[(usize, f64)],
+        values: &[bool],
+        queries: &[&Fact],
+        expressions: &[Rule<'a>],
+    ) -> Result<Vec<bool>, Error> {
+        self.facts.restore(initial);
+        for ((index, _), value) in uncertain.iter().zip(values.iter()) {
+            let fact = &self.facts.fact_arr[*index];
+            fact.state.set(*value);
+            fact.determined.set(true);
+        }
+        let mut truths: Vec<bool> = solver::solve(queries.to_vec(), self.rules, self.options)?
+            .iter()
+            .map(|fact| fact.solved_state() == State::True)
+            .collect();
+        for expression in expressions.iter() {
+            truths.push(
+                solver::solve_expression(expression, self.rules, self.options)? == Some(true),
+            );
+        }
+        Ok(truths)
+    }
+
+    // Probabilities of the queries followed by those of the expressions,
+    // the facts being left as the initial ones.
+    pub fn solve(
+        &self,
+        initial: &[Fact],
+        queries: Vec<&'a Fact>,
+        expressions: &[Rule<'a>],
+    ) -> Result<Vec<f64>, Error> {
+        self.trace.clear();
+        let mut pending = queries.clone();
+        for expression in expressions.iter() {
+            pending.extend(expression.lhs.iter().filter_map(|t| t.fact));
+        }
+        let relevant = self.relevant(pending);
+        let uncertain: Vec<(usize, f64)> = initial
+            .iter()
+            .enumerate()
+            .filter(|(_, fact)| fact.certainty.get().is_some() && relevant.contains(&fact.name))
+            .map(|(index, fact)| (index, weighted::degree(fact)))
+            .collect();
+        for (index, prior) in uncertain.iter() {
+            self.trace.note(format!(
+                "{} has prior probability {:.2}.",
+                initial[*index].describe(),
+                prior
+            ));
+        }
+        let counted = match self.options.samples {
+            Some(_) => None,
+            None => self.count(initial, &uncertain, &queries, expressions),
+        };
+        let worlds = match uncertain.len() {
+            n if n < usize::BITS as usize => 1usize << n,
+            _ => usize::MAX,
+        };
+        let sampled = counted.is_none() && (self.options.samples.is_some() || worlds > SAMPLES);
+        let probabilities = match counted {
+            Some(probabilities) => probabilities,
+            None if sampled => self.sample(initial, &uncertain, &queries, expressions)?,
+            None => self.enumerate(initial, &uncertain, &queries, expressions)?,
+        };
+        self.facts.restore(initial);
+        self.sampled.set(sampled);
+        let names = queries.iter().map(|fact| fact.describe()).chain(
+            expressions
+                .iter()
+                .map(|expression| expression.label.clone()),
+        );
+        for (name, probability) in names.zip(probabilities.iter()) {
+            self.trace
+                .note(format!("{} has probability {:.2}.", name, probability));
+        }
+        Ok(probabilities)
+    }
+}
@@ -0,0 +1,158 @@
+use crate::diff;
+use crate::expert_system;
+use crate::facts::Fact;
+use crate::options::Options;
+use crate::print;
+use crate::scenario::ScenarioResult;
+
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+const IGNORED_EXTENSIONS: [&str; 5] = ["refout", "referr", "userout", "py", "dot"];
+
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    Skip,
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+// Output of a run as printed on stdout, or the error it stops on.
+pub fn actual_output(input: &Path, options: &Options) -> Result<String, Error> {
+    expert_system::solve_file(&input.to_string_lossy(), options)
+        .map(|results| print::scenarios_to_string(&results))
+}
+
+// The output is compared with the .refout file and the error with the
+// .referr one, a missing file expecting nothing.
+pub fn run_case(input: &Path, options: &Options) -> Result<Outcome, Error> {
+    let refout = with_extension(input, "refout");
+    let referr = with_extension(input, "referr");
+    if !refout.is_file() && !referr.is_file() {
+        return check_case(input, options);
+    }
+    let (stdout, stderr) = match actual_output(input, options) {
+        Ok(output) => (output, String::new()),
+        Err(error) => (String::new(), error.to_string()),
+    };
+    let read = |path: &Path| match path.is_file() {
+        true => fs::read_to_string(path),
+        false => Ok(String::new()),
+    };
+    let mut diff = diff::unified(
+        &read(&refout)?,
+        &stdout,
+        &refout.to_string_lossy(),
+        &input.to_string_lossy(),
+    );
+    diff.push_str(&diff::unified(
+        read(&referr)?.trim_end(),
+        &stderr,
+        &referr.to_string_lossy(),
+        &input.to_string_lossy(),
+    ));
+    if diff.is_empty() {
+        return Ok(Outcome::Pass);
+    }
+    Ok(Outcome::Fail(diff))
+}
+
+pub fn check_case(input: &Path, options: &Options) -> Result<Outcome, Error> {
+    let results = match expert_system::solve_file(&input.to_string_lossy(), options) {
+        Ok(results) => results,
+        Err(error) => return Ok(Outcome::Fail(print::error_to_string(&error))),
+    };
+    let checked: Vec<ScenarioResult> = results
+        .iter()
+        .map(|result| ScenarioResult {
+            name: result.name.clone(),
+            solved_queries: result
+                .solved_queries
+                .iter()
+                .filter(|fact| fact.expected.get().is_some())
+                .map(Fact::copy)
+                .collect(),
+            expressions: Vec::new(),
+            explanation: String::new(),
+        })
+        .filter(|result| !result.solved_queries.is_empty())
+        .collect();
+    if checked.is_empty() {
+        return Ok(Outcome::Skip);
+    }
+    let diff = diff::unified(
+        &print::expectations_to_string(&checked),
+        &print::scenarios_to_string(&checked),
+        "expected",
+        &input.to_string_lossy(),
+    );
+    if diff.is_empty() {
+        return Ok(Outcome::Pass);
+    }
+    Ok(Outcome::Fail(diff))
+}
+
+fn inputs_of_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let ignored = match path.extension() {
+            Some(ext) => IGNORED_EXTENSIONS.iter().any(|ignored| ext == *ignored),
+            None => false,
+        };
+        if path.is_file() && !ignored {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+fn report(input: &Path, outcome: Outcome) -> Option<bool> {
+    match outcome {
+        Outcome::Pass => {
+            println!("PASS {}", input.display());
+            Some(true)
+        }
+        Outcome::Fail(diff) => {
+            println!("FAIL {}", input.display());
+            print!("{}", diff);
+            Some(false)
+        }
+        Outcome::Skip => None,
+    }
+}
+
+pub fn check(filename: &str, options: &Options) -> Result<bool, Error> {
+    let input = Path::new(filename);
+    match report(input, check_case(input, options)?) {
+        Some(passed) => Ok(passed),
+        None => {
+            println!("SKIP {} (no expectation)", input.display());
+            Ok(true)
+        }
+    }
+}
+
+pub fn run(dir: &str, options: &Options) -> Result<bool, Error> {
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for input in inputs_of_dir(Path::new(dir))? {
+        match report(&input, run_case(&input, options)?) {
+            Some(true) => passed += 1,
+            Some(false) => failed += 1,
+            None => skipped += 1,
+        }
+    }
+    println!(
+        "{}: {} passed, {} failed, {} skipped",
+        dir, passed, failed, skipped
+    );
+    Ok(failed == 0)
+}
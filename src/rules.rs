@@ -2,65 +2,206 @@ pub mod rule;
 
 use crate::checker;
 use crate::facts::Facts;
+use crate::lexer;
 use crate::options::Options;
+use crate::syntax::tokens::{tokenize, SyntaxToken, TokenKind};
 use core::slice::Iter;
-use rule::{token::Operand, Rule, Side};
+use rule::{side_to_infix, token::Operand, Rule, Side};
 use std::io::{Error, ErrorKind};
 
 #[derive(Default)]
-pub struct Rules<'rules>(Vec<Rule<'rules>>);
+pub struct Rules<'rules> {
+    rules: Vec<Rule<'rules>>,
+    // Rules written in the knowledge base, implicit reverse rules aside.
+    written: usize,
+    location: Option<String>,
+}
+
+fn label(line: &str) -> Result<(Option<String>, &str), Error> {
+    let line = line.trim_start();
+    if !line.starts_with('[') {
+        return Ok((None, line));
+    }
+    match line.find(']') {
+        Some(end) if lexer::is_valid_name(line[1..end].trim()) => {
+            Ok((Some(line[1..end].trim().to_string()), &line[end + 1..]))
+        }
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Rules: invalid label (at {})", line),
+        )),
+    }
+}
+
+// Threshold of a cardinality, read from the "(k," after its name.
+fn threshold(tokens: &[SyntaxToken]) -> Option<u8> {
+    match tokens {
+        [open, k, comma, ..]
+            if open.kind == TokenKind::LParen
+                && matches!(k.kind, TokenKind::Number | TokenKind::Constant)
+                && comma.kind == TokenKind::Comma =>
+        {
+            k.text.parse().ok()
+        }
+        _ => None,
+    }
+}
 
 impl<'rules> Rules<'rules> {
     pub fn new() -> Self {
-        Rules(Vec::new())
+        Rules {
+            rules: Vec::new(),
+            written: 0,
+            location: None,
+        }
     }
 
-    pub fn set_rule(
-        &mut self,
+    // Provenance given to the next rules, as "file:line".
+    pub fn set_location(&mut self, location: Option<String>) {
+        self.location = location;
+    }
+
+    // Reads a rule from the tokens of the syntax tree, the returned side is
+    // Rhs once an impliance was met and Lhs when there is none.
+    fn read_rule(
         facts: &'rules Facts,
+        text: &str,
+        body: &str,
         line: &str,
         options: &Options,
-    ) -> Result<(), Error> {
+    ) -> Result<(Rule<'rules>, Side, bool), Error> {
         let mut side = Side::Lhs;
         let mut rule = Rule::new();
         let mut is_equivalent = false;
+        let error = |message: String, at: usize| {
+            lexer::positioned(Error::new(ErrorKind::InvalidData, message), at)
+        };
 
-        for c in line.chars() {
-            if side == Side::Pending || side == Side::Bidirectional {
-                if side == Side::Bidirectional {
-                    is_equivalent = true;
+        // An implication inside brackets is an operator of the expression.
+        let balanced = body.matches('(').count() == body.matches(')').count();
+        // Open brackets, true for the argument list of a cardinality.
+        let mut parens: Vec<bool> = Vec::new();
+        let tokens: Vec<SyntaxToken> = tokenize(body, text.len() - body.len())
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace && t.kind != TokenKind::Newline)
+            .collect();
+        let mut i = 0;
+        while let Some(token) = tokens.get(i) {
+            let at = token.span.start;
+            i += 1;
+            match token.kind {
+                TokenKind::Comment => {
+                    if options.comment && !options.file {
+                        println!("{}", line);
+                    }
+                    break;
                 }
-                checker::impliance(&mut side, c)?;
-                continue;
-            }
-            if c.is_whitespace() {
-                continue;
-            } else if c.is_uppercase() {
-                rule.push(side, None, Some(facts.get(c)));
-            } else {
-                match c {
-                    '(' => rule.push(side, Some(Operand::Opening), None),
-                    ')' => rule.push(side, Some(Operand::Closing), None),
-                    '!' => rule.push(side, Some(Operand::Not), None),
-                    '|' => rule.push(side, Some(Operand::Or), None),
-                    '^' => rule.push(side, Some(Operand::Xor), None),
-                    '+' => rule.push(side, Some(Operand::And), None),
-                    '#' => {
-                        if options.comment && !options.file {
-                            println!("{}", line);
+                TokenKind::Keyword => {
+                    let op = threshold(&tokens[i..])
+                        .and_then(|k| Operand::cardinality(&token.text, k))
+                        .ok_or_else(|| {
+                            let message =
+                                format!("Rules: {} expects a threshold then facts", token.text);
+                            error(message, at)
+                        })?;
+                    i += 3;
+                    parens.push(true);
+                    rule.push(side, Some(op), None);
+                    rule.push(side, Some(Operand::Opening), None);
+                }
+                TokenKind::Constant => {
+                    rule.push_constant(side, token.text == "TRUE" || token.text == "1")
+                }
+                TokenKind::Name => {
+                    if let Some((operator, threshold, len)) =
+                        lexer::comparison(&text[token.span.end..])
+                    {
+                        if side != Side::Lhs {
+                            return Err(error(
+                                format!("Rules: comparison in conclusion (at {})", line),
+                                at,
+                            ));
+                        }
+                        let fact = facts.comparison(&token.text, operator, threshold);
+                        rule.push(
+                            side,
+                            None,
+                            Some(fact.map_err(|e| lexer::positioned(e, at))?),
+                        );
+                        let end = token.span.end + len;
+                        while tokens.get(i).is_some_and(|t| t.span.start < end) {
+                            i += 1;
                         }
-                        break;
+                        continue;
                     }
-                    '<' | '=' => checker::impliance(&mut side, c)?,
-                    _ => {
-                        return Err(Error::new(
-                            ErrorKind::InvalidData,
-                            format!("Rules: unexpected char (at {})", line),
-                        ))
+                    let read = facts.name_facts(&token.text);
+                    for fact in read.map_err(|e| lexer::positioned(e, at))? {
+                        rule.push(side, None, Some(fact));
                     }
-                };
+                }
+                TokenKind::LParen => {
+                    parens.push(false);
+                    rule.push(side, Some(Operand::Opening), None)
+                }
+                TokenKind::RParen => {
+                    if rule.last(side).and_then(|t| t.operand) == Some(Operand::Comma) {
+                        return Err(error(format!("Rules: missing operand (at {})", line), at));
+                    }
+                    parens.pop();
+                    rule.push(side, Some(Operand::Closing), None)
+                }
+                TokenKind::Comma if parens.last() == Some(&true) => {
+                    rule.push(side, Some(Operand::Comma), None)
+                }
+                TokenKind::Imply | TokenKind::Equiv if balanced && !parens.is_empty() => {
+                    rule.push(side, token.kind.binary_operand(), None)
+                }
+                TokenKind::Imply | TokenKind::Equiv if side == Side::Rhs => {
+                    return Err(error("Rules: <, =, or > oversupplied".to_string(), at))
+                }
+                TokenKind::Imply | TokenKind::Equiv => {
+                    side = Side::Rhs;
+                    is_equivalent = token.kind == TokenKind::Equiv;
+                }
+                // '=' and '<' only start an impliance.
+                TokenKind::Equals | TokenKind::Compare if token.text.starts_with(['=', '<']) => {
+                    return Err(error("Rules: impliance wrong format".to_string(), at))
+                }
+                TokenKind::Not => rule.push(side, Some(Operand::Not), None),
+                kind => match kind.binary_operand() {
+                    Some(op) => rule.push(side, Some(op), None),
+                    None => return Err(error(format!("Rules: unexpected char (at {})", line), at)),
+                },
             }
         }
+        Ok((rule, side, is_equivalent))
+    }
+
+    pub fn set_rule(
+        &mut self,
+        facts: &'rules Facts,
+        line: &str,
+        options: &Options,
+    ) -> Result<(), Error> {
+        let shown = if line.contains('\n') {
+            line.lines().map(str::trim).collect::<Vec<_>>().join(" ")
+        } else {
+            line.to_string()
+        };
+        let (certainty, text) = match lexer::certainty(line) {
+            // Priors go on initial facts, a rule always holds.
+            Some((_, factor)) if options.probability => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Probability: weighted rule (at [{}])", factor),
+                ))
+            }
+            Some((open, factor)) => (Some(checker::certainty(factor, options)?), &line[..open]),
+            None => (None, line),
+        };
+        let (label, body) = label(text)?;
+        let (mut rule, side, is_equivalent) = Rules::read_rule(facts, text, body, &shown, options)?;
+        let line = shown.as_str();
         if side != Side::Rhs {
             return Err(Error::new(
                 ErrorKind::InvalidData,
@@ -69,30 +210,80 @@ impl<'rules> Rules<'rules> {
         }
         checker::rule_composition(&rule.lhs, line)?;
         checker::rule_composition(&rule.rhs, line)?;
+        checker::conclusion(&rule.rhs, line)?;
+        if is_equivalent {
+            checker::conclusion(&rule.lhs, line)?;
+        }
+        rule.label = label.unwrap_or_else(|| format!("r{}", self.written + 1));
+        if self.rules.iter().any(|r| r.label == rule.label) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Rules: label doublon ({})", rule.label),
+            ));
+        }
+        rule.location = self.location.clone();
+        rule.certainty = certainty;
+        self.written += 1;
         if is_equivalent {
             let mut implicit_rule = Rule::new();
             implicit_rule.lhs = rule.rhs.clone();
             implicit_rule.rhs = rule.lhs.clone();
-            self.0.push(implicit_rule);
+            implicit_rule.label = format!("{}.reverse", rule.label);
+            implicit_rule.location = rule.location.clone();
+            implicit_rule.certainty = rule.certainty;
+            self.rules.push(implicit_rule);
         }
-        self.0.push(rule);
+        self.rules.push(rule);
         Ok(())
     }
 
-    pub fn iter(&self) -> Iter<Rule<'rules>> {
-        self.0.iter()
+    // An expression query, "?(A + !B) | C", is read as a rule body without
+    // conclusion and returned in reversed RPN.
+    pub fn expression(
+        facts: &'rules Facts,
+        line: &str,
+        options: &Options,
+    ) -> Result<Rule<'rules>, Error> {
+        let body = line.trim_start();
+        let body = body.strip_prefix('?').unwrap_or(body);
+        let shown = line.trim();
+        let (mut rule, side, _) = Rules::read_rule(facts, line, body, shown, options)?;
+        if side != Side::Lhs {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Queries: impliance in expression (at {})", shown),
+            ));
+        }
+        checker::rule_composition(&rule.lhs, shown)?;
+        rule.as_rpn()?;
+        if rule.lhs.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Queries: empty expression (at {})", shown),
+            ));
+        }
+        rule.label = side_to_infix(&rule.lhs);
+        Ok(rule)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Rule<'rules>> {
+        self.rules.get(index)
+    }
+
+    pub fn iter(&self) -> Iter<'_, Rule<'rules>> {
+        self.rules.iter()
     }
 
     pub fn as_reverse_polish_notation(&mut self) -> Result<(), Error> {
-        for rule in self.0.iter_mut() {
-            rule.as_rpn()?;
+        for rule in self.rules.iter_mut() {
+            rule.as_rpn().map_err(|e| rule.error(e))?;
         }
         Ok(())
     }
 
     pub fn print(&self) {
         println!("PRINTING RULES");
-        for rule in &self.0 {
+        for rule in &self.rules {
             rule.print();
         }
         println!();
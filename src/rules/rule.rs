@@ -5,18 +5,88 @@ use crate::facts::Fact;
 use std::io::Error;
 use token::{Operand, Token};
 
+pub fn is_comparison(token: Option<&Token>) -> bool {
+    token
+        .and_then(|token| token.fact)
+        .is_some_and(|fact| fact.comparison.is_some())
+}
+
+fn infix_rec(tokens: &[Token], i: &mut usize, is_root: bool) -> String {
+    let token = match tokens.get(*i) {
+        Some(token) => *token,
+        None => return String::new(),
+    };
+    *i += 1;
+    match token.operand {
+        // A negated comparison keeps brackets, "!(temperature > 38)".
+        Some(Operand::Not) if is_comparison(tokens.get(*i)) => {
+            format!("!({})", infix_rec(tokens, i, false))
+        }
+        Some(Operand::Not) => format!("!{}", infix_rec(tokens, i, false)),
+        Some(Operand::AtLeast(k)) | Some(Operand::AtMost(k)) | Some(Operand::Exactly(k)) => {
+            let list = infix_rec(tokens, i, true);
+            format!("{}({}, {})", token.get_op_str(), k, list)
+        }
+        Some(Operand::Comma) => {
+            let rhs = infix_rec(tokens, i, true);
+            let lhs = infix_rec(tokens, i, true);
+            format!("{}, {}", lhs, rhs)
+        }
+        Some(op) => {
+            let rhs = infix_rec(tokens, i, false);
+            let lhs = infix_rec(tokens, i, false);
+            // A nested implication keeps its brackets to stay apart from
+            // the one of the rule.
+            if is_root && op != Operand::Imply && op != Operand::Equiv {
+                format!("{} {} {}", lhs, token.get_op_str(), rhs)
+            } else {
+                format!("({} {} {})", lhs, token.get_op_str(), rhs)
+            }
+        }
+        None => token.get_token_str(),
+    }
+}
+
+// Walks a conclusion in reversed RPN and returns whether the implied fact
+// appears in it negated.
+fn conclusion_rec(tokens: &[Token], i: &mut usize, is_not: bool, implied: &Fact) -> Option<bool> {
+    let token = tokens.get(*i)?;
+    *i += 1;
+    match token.operand {
+        None => match token.fact {
+            Some(fact) if fact.name == implied.name => Some(is_not),
+            _ => None,
+        },
+        Some(Operand::Not) => conclusion_rec(tokens, i, !is_not, implied),
+        Some(_) => {
+            let rhs = conclusion_rec(tokens, i, is_not, implied);
+            let lhs = conclusion_rec(tokens, i, is_not, implied);
+            rhs.or(lhs)
+        }
+    }
+}
+
+// Renders one side of a rule, stored as reversed RPN, back in infix notation.
+pub fn side_to_infix(tokens: &[Token]) -> String {
+    let mut i = 0;
+    infix_rec(tokens, &mut i, true)
+}
+
 #[derive(Copy, Clone, PartialEq)]
 pub enum Side {
     Lhs,
     Rhs,
-    Pending,
-    Bidirectional,
 }
 
 #[derive(Default, Clone, Debug)]
 pub struct Rule<'rule> {
     pub lhs: Vec<Token<'rule>>,
     pub rhs: Vec<Token<'rule>>,
+    pub label: String,
+    // "file:line" of the rule in the knowledge base, None for built rules.
+    pub location: Option<String>,
+    // Certainty factor written after the conclusion, "A => B [0.8]".
+    pub certainty: Option<f64>,
 }
 
 impl<'rule> Rule<'rule> {
@@ -24,6 +94,24 @@ impl<'rule> Rule<'rule> {
         Rule {
             lhs: Vec::new(),
             rhs: Vec::new(),
+            label: String::new(),
+            location: None,
+            certainty: None,
+        }
+    }
+
+    pub fn provenance(&self) -> String {
+        match &self.location {
+            Some(location) => format!("{} ({})", self.label, location),
+            None => self.label.clone(),
+        }
+    }
+
+    pub fn error(&self, error: Error) -> Error {
+        let message = format!("Rule {}: {}", self.label, error);
+        match &self.location {
+            Some(location) => Error::new(error.kind(), format!("{}: {}", location, message)),
+            None => Error::new(error.kind(), message),
         }
     }
 
@@ -43,28 +131,43 @@ impl<'rule> Rule<'rule> {
         }
     }
 
+    pub fn push_constant(&mut self, side: Side, value: bool) {
+        if side == Side::Lhs {
+            self.lhs.push(Token::new_constant(value));
+        } else {
+            self.rhs.push(Token::new_constant(value));
+        }
+    }
+
+    pub fn last(&self, side: Side) -> Option<&Token<'rule>> {
+        if side == Side::Lhs {
+            self.lhs.last()
+        } else {
+            self.rhs.last()
+        }
+    }
+
     pub fn implies_fact(&self, implied_fact: &Fact) -> bool {
-        let mut is_not = false;
-        for token in self.rhs.iter() {
-            if let Some(fact) = token.fact {
-                if fact.letter == implied_fact.letter {
-                    fact.reverse_state.set(is_not);
-                    return true;
-                }
-                is_not = false;
-            }
-            if let Some(op) = token.operand {
-                if op == Operand::Not {
-                    is_not = !is_not;
-                } else {
-                    is_not = false;
-                }
+        let mut i = 0;
+        match conclusion_rec(&self.rhs, &mut i, false, implied_fact) {
+            Some(is_not) => {
+                implied_fact.reverse_state.set(is_not);
+                true
             }
+            None => false,
         }
-        false
+    }
+
+    pub fn to_infix(&self) -> String {
+        format!(
+            "{} => {}",
+            side_to_infix(&self.lhs),
+            side_to_infix(&self.rhs)
+        )
     }
 
     pub fn print(&self) {
+        print!("{}: ", self.provenance());
         for token in &self.lhs {
             token.print();
         }
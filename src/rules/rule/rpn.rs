@@ -6,8 +6,8 @@ pub fn apply_on_vec<'rule>(tokens: &[Token<'rule>]) -> Result<Vec<Token<'rule>>,
     let mut tmp: Vec<Operand> = Vec::new();
 
     for token in tokens {
-        if token.fact.is_some() {
-            ret.push(token.clone());
+        if token.is_value() {
+            ret.push(*token);
             continue;
         }
         match token.operand {
@@ -32,16 +32,26 @@ pub fn apply_on_vec<'rule>(tokens: &[Token<'rule>]) -> Result<Vec<Token<'rule>>,
     Ok(ret)
 }
 
-fn op_priority(op: Operand) -> u8 {
+pub fn op_priority(op: Operand) -> u8 {
     match op {
-        Operand::Not => 1,
-        Operand::And => 2,
-        Operand::Or => 3,
-        Operand::Xor => 4,
-        _ => 5,
+        Operand::Not | Operand::AtLeast(_) | Operand::AtMost(_) | Operand::Exactly(_) => 1,
+        Operand::And | Operand::Nand => 2,
+        Operand::Or | Operand::Nor => 3,
+        Operand::Xor | Operand::Xnor => 4,
+        Operand::Imply => 5,
+        Operand::Equiv => 6,
+        Operand::Comma => 7,
+        _ => 8,
     }
 }
 
+// Whether op, about to be stacked, first unstacks last. Implication is
+// right associative: A => B => C reads A => (B => C).
+fn unstacks(op: Operand, last: Operand) -> bool {
+    op_priority(op) > op_priority(last)
+        || (op_priority(op) == op_priority(last) && op != Operand::Imply)
+}
+
 fn unstack_to_opening(ret: &mut Vec<Token>, tmp: &mut Vec<Operand>) -> Result<(), Error> {
     while !tmp.is_empty() {
         let last = *tmp.last().unwrap();
@@ -67,7 +77,7 @@ fn unstack_with_lvl(ret: &mut Vec<Token>, tmp: &mut Vec<Operand>, op: Operand) {
 
         ret.push(Token::new_op(last));
         if let Some(last) = tmp.last() {
-            if op_priority(op) < op_priority(*last) {
+            if !unstacks(op, *last) {
                 break;
             }
         }
@@ -86,9 +96,9 @@ fn sort_operand(ret: &mut Vec<Token>, tmp: &mut Vec<Operand>, op: Operand) -> Re
         return Ok(());
     }
     if op != Operand::Opening
-        && op != Operand::Not
+        && !op.is_unary()
         && !tmp.is_empty()
-        && op_priority(op) >= op_priority(*tmp.last().unwrap())
+        && unstacks(op, *tmp.last().unwrap())
     {
         unstack_with_lvl(ret, tmp, op);
     }
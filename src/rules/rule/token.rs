@@ -6,25 +6,59 @@ pub enum Operand {
     And,
     Xor,
     Or,
+    Nand,
+    Nor,
+    Xnor,
+    Imply,
+    Equiv,
+    AtLeast(u8),
+    AtMost(u8),
+    Exactly(u8),
+    Comma,
     Opening,
     Closing,
 }
 
+impl Operand {
+    pub fn is_unary(self) -> bool {
+        matches!(
+            self,
+            Operand::Not | Operand::AtLeast(_) | Operand::AtMost(_) | Operand::Exactly(_)
+        )
+    }
+
+    pub fn cardinality(name: &str, threshold: u8) -> Option<Operand> {
+        match name {
+            "atleast" => Some(Operand::AtLeast(threshold)),
+            "atmost" => Some(Operand::AtMost(threshold)),
+            "exactly" => Some(Operand::Exactly(threshold)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Token<'a> {
     pub operand: Option<Operand>,
     pub fact: Option<&'a Fact>,
+    // TRUE or FALSE written in a rule.
+    pub constant: Option<bool>,
 }
 
 impl<'a> Token<'a> {
-    pub fn new(operand: Option<Operand>, fact: Option<&Fact>) -> Token {
-        Token { operand, fact }
+    pub fn new(operand: Option<Operand>, fact: Option<&Fact>) -> Token<'_> {
+        Token {
+            operand,
+            fact,
+            constant: None,
+        }
     }
 
     pub fn new_op(operand: Operand) -> Token<'a> {
         Token {
             operand: Some(operand),
             fact: None,
+            constant: None,
         }
     }
 
@@ -32,24 +66,38 @@ impl<'a> Token<'a> {
         Token {
             operand: None,
             fact: Some(fact),
+            constant: None,
+        }
+    }
+
+    pub fn new_constant(value: bool) -> Token<'a> {
+        Token {
+            operand: None,
+            fact: None,
+            constant: Some(value),
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.fact.is_none() && self.operand.is_none()
+        self.fact.is_none() && self.operand.is_none() && self.constant.is_none()
     }
 
     pub fn is_fact(&self) -> bool {
         self.fact.is_some()
     }
 
+    // Operands of an expression: facts and constants.
+    pub fn is_value(&self) -> bool {
+        self.fact.is_some() || self.constant.is_some()
+    }
+
     pub fn is_operand(&self) -> bool {
         self.operand.is_some()
     }
 
     pub fn is_cumulable(&self) -> bool {
         if let Some(op) = self.operand {
-            return op == Operand::Not || op == Operand::Opening || op == Operand::Closing;
+            return op.is_unary() || op == Operand::Opening || op == Operand::Closing;
         }
         false
     }
@@ -68,33 +116,44 @@ impl<'a> Token<'a> {
         }
     }
 
-    pub fn get_op_char(&self) -> char {
+    pub fn get_op_str(&self) -> &'static str {
         if let Some(op) = self.operand {
             return match op {
-                Operand::Not => '!',
-                Operand::And => '+',
-                Operand::Or => '|',
-                Operand::Xor => '^',
-                Operand::Opening => '(',
-                Operand::Closing => ')',
+                Operand::Not => "!",
+                Operand::And => "+",
+                Operand::Or => "|",
+                Operand::Xor => "^",
+                Operand::Nand => "!+",
+                Operand::Nor => "!|",
+                Operand::Xnor => "!^",
+                Operand::Imply => "=>",
+                Operand::Equiv => "<=>",
+                Operand::AtLeast(_) => "atleast",
+                Operand::AtMost(_) => "atmost",
+                Operand::Exactly(_) => "exactly",
+                Operand::Comma => ",",
+                Operand::Opening => "(",
+                Operand::Closing => ")",
             };
         }
-        self.fact.unwrap().letter
+        panic!("Fact token has no operand");
     }
 
-    pub fn get_token_char(&self) -> char {
+    pub fn get_token_str(&self) -> String {
         if let Some(fact) = self.fact {
-            fact.letter
+            fact.name.clone()
+        } else if let Some(value) = self.constant {
+            String::from(if value { "TRUE" } else { "FALSE" })
         } else {
-            self.get_op_char()
+            self.get_op_str().to_string()
         }
     }
 
     pub fn print(&self) {
         if let Some(fact) = self.fact {
-            print!("{} ", fact.letter);
-        } else if self.operand.is_some() {
-            print!("{} ", self.get_op_char());
+            print!("{} ", fact.name);
+        } else if self.operand.is_some() || self.constant.is_some() {
+            print!("{} ", self.get_token_str());
         } else {
             panic!("Empty Token");
         }
@@ -102,20 +161,20 @@ impl<'a> Token<'a> {
 
     pub fn print_state(&self) {
         if let Some(fact) = self.fact {
-            print!("{}({}) ", fact.state.get(), fact.letter);
-        } else if self.operand.is_some() {
-            print!("{} ", self.get_op_char());
+            print!("{}({}) ", fact.state.get(), fact.name);
+        } else if self.operand.is_some() || self.constant.is_some() {
+            print!("{} ", self.get_token_str());
         } else {
             panic!("Empty Token");
         }
     }
 
     pub fn cmp_tok(&self, tok: &Token) -> bool {
-        if self.fact.is_some() && tok.fact.is_some() {
-            return self.fact.unwrap().letter == tok.fact.unwrap().letter;
-        } else if self.operand.is_some() && tok.operand.is_some() {
-            return self.operand.unwrap() == tok.operand.unwrap();
+        match (self.fact, tok.fact, self.operand, tok.operand) {
+            (Some(fact), Some(tok_fact), _, _) => fact.name == tok_fact.name,
+            (_, _, Some(op), Some(tok_op)) => op == tok_op,
+            (None, None, None, None) => self.constant == tok.constant,
+            _ => false,
         }
-        false
     }
 }
@@ -0,0 +1,72 @@
+use crate::facts::{Fact, Facts, Measure, State};
+use crate::rules::rule::Rule;
+
+use std::io::{Error, ErrorKind};
+
+pub struct Scenario<'a> {
+    pub name: Option<String>,
+    pub facts: Vec<Fact>,
+    // Expression queries, evaluated once the facts are solved.
+    pub expressions: Vec<Rule<'a>>,
+    pub has_initial_facts: bool,
+    pub has_queries: bool,
+}
+
+pub struct ScenarioResult {
+    pub name: Option<String>,
+    pub solved_queries: Vec<Fact>,
+    // Each expression query with its value, and its certainty factor
+    // with --certainty or its truth degree with --fuzzy.
+    pub expressions: Vec<(String, State, Option<Measure>)>,
+    pub explanation: String,
+}
+
+impl<'a> Scenario<'a> {
+    pub fn new(name: Option<String>) -> Self {
+        Scenario {
+            name,
+            facts: Vec::new(),
+            expressions: Vec::new(),
+            has_initial_facts: false,
+            has_queries: false,
+        }
+    }
+
+    pub fn from_line(line: &str) -> Result<Self, Error> {
+        let name = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let name = name.trim().trim_start_matches('@').trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Scenario: invalid name (at {})", line),
+            ));
+        }
+        Ok(Scenario::new(Some(name.to_string())))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.has_initial_facts && !self.has_queries
+    }
+
+    pub fn close(&mut self, facts: &Facts) -> Result<(), Error> {
+        if !self.has_initial_facts {
+            return Err(self.error(Error::new(
+                ErrorKind::InvalidData,
+                "Parser: no initial fact",
+            )));
+        }
+        self.facts = facts.snapshot();
+        facts.reset();
+        Ok(())
+    }
+
+    pub fn error(&self, error: Error) -> Error {
+        match &self.name {
+            Some(name) => Error::new(error.kind(), format!("Scenario {}: {}", name, error)),
+            None => error,
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use crate::expert_system;
+use crate::explain::{Explainer, Template};
+use crate::facts::{Fact, Facts, State};
+use crate::json::Value;
+use crate::lexer;
+use crate::options::Options;
+use crate::rules::Rules;
+use crate::session::Session;
+use crate::solver;
+use crate::source::Source;
+
+use std::io::{prelude::*, BufReader, Error, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 8080;
+pub const MAX_BODY: usize = 1 << 20;
+pub const MAX_HEAD: u64 = 8 * 1024;
+// Requests are served one at a time, a client can't hold the server longer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// A knowledge base parsed once when the server starts. Its facts live as
+// long as the server, so that its rules can borrow them.
+pub struct KnowledgeBase {
+    pub name: String,
+    facts: &'static Facts,
+    rules: Rules<'static>,
+    // Initial facts of the file, replaced by the ones posted.
+    initial: Vec<Fact>,
+    session: Session<'static>,
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+// Length of the body announced by a client, or the status refusing it.
+pub fn content_length(value: &str) -> Result<usize, u16> {
+    match value.parse() {
+        Ok(length) if length <= MAX_BODY => Ok(length),
+        Ok(_) => Err(413),
+        Err(_) => Err(400),
+    }
+}
+
+// Method, path and body length of a request, read from its first MAX_HEAD
+// bytes, or the status refusing it.
+pub fn read_head<R: BufRead>(
+    reader: &mut R,
+) -> Result<Result<(String, String, usize), u16>, Error> {
+    let mut head = reader.take(MAX_HEAD);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    let mut words = request_line.split_whitespace();
+    let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    let mut length = Ok(0);
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 || !header.ends_with('\n') {
+            // Cut by the limit rather than by the end of the stream.
+            if head.limit() == 0 {
+                return Ok(Err(431));
+            }
+            break;
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = content_length(value.trim());
+            }
+        }
+    }
+    Ok(length.map(|length| (method.to_string(), path.to_string(), length)))
+}
+
+// Reads of a request, which all together can't take longer than TIMEOUT.
+struct Deadline {
+    stream: TcpStream,
+    end: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let left = self.end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(Error::new(ErrorKind::TimedOut, "Serve: request timed out"));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+fn state(fact: &Fact) -> Value {
+    Value::string(fact.solved_state().as_str())
+}
+
+fn strings<'a>(body: &'a Value, key: &str) -> Result<Option<Vec<&'a str>>, Error> {
+    match body.get(key) {
+        Value::Null => Ok(None),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .ok_or_else(|| invalid(format!("Serve: {} expects strings", key)))
+            })
+            .collect::<Result<Vec<&str>, Error>>()
+            .map(Some),
+        _ => Err(invalid(format!("Serve: {} expects an array", key))),
+    }
+}
+
+impl KnowledgeBase {
+    pub fn load(path: &Path, options: &Options) -> Result<Self, Error> {
+        let source = Source::load(path)?;
+        // Leaked on purpose: bases are loaded once and kept until the process exits.
+        let facts: &'static Facts = Box::leak(Box::new(Facts::from_source(&source)));
+        let (rules, scenarios) = expert_system::parse_source(&source, facts, options)?;
+        facts.set_scope(None);
+        let name = path
+            .file_name()
+            .map_or(path.to_string_lossy(), |name| name.to_string_lossy())
+            .to_string();
+        let initial = scenarios
+            .into_iter()
+            .next()
+            .map_or_else(Vec::new, |s| s.facts);
+        facts.restore(&initial);
+        let session = Session::new(facts, &rules);
+        Ok(KnowledgeBase {
+            name,
+            facts,
+            rules,
+            initial,
+            session,
+        })
+    }
+
+    fn facts_to_json(&self) -> Value {
+        let facts = self
+            .facts
+            .fact_arr
+            .iter()
+            .zip(self.initial.iter())
+            .map(|(fact, initial)| {
+                let description = fact
+                    .description
+                    .as_deref()
+                    .map_or(Value::Null, Value::string);
+                let value = initial.value.get().map_or(Value::Null, Value::Number);
+                Value::object(vec![
+                    ("name", Value::string(&fact.name)),
+                    ("description", description),
+                    ("initial", state(initial)),
+                    ("value", value),
+                ])
+            })
+            .collect();
+        Value::object(vec![("facts", Value::Array(facts))])
+    }
+
+    fn rules_to_json(&self) -> Value {
+        let rules = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let location = rule.location.as_deref().map_or(Value::Null, Value::string);
+                Value::object(vec![
+                    ("label", Value::string(&rule.label)),
+                    ("rule", Value::String(rule.to_infix())),
+                    ("location", location),
+                ])
+            })
+            .collect();
+        Value::object(vec![("rules", Value::Array(rules))])
+    }
+
+    // Sets the facts to the given "=" line, the session is left as is
+    // without one.
+    fn set_initial(&self, names: Option<Vec<&str>>, options: &Options) -> Result<(), Error> {
+        if let Some(names) = names {
+            self.facts.reset();
+            self.facts
+                .set_initial_facts(&format!("={}", names.join(" ")), options)?;
+        }
+        for fact in self.facts.fact_arr.iter() {
+            fact.queried.set(false);
+            fact.expected.set(None);
+        }
+        Ok(())
+    }
+
+    fn post_facts(&mut self, body: &Value, options: &Options) -> Result<Value, Error> {
+        let names = strings(body, "facts")?.unwrap_or_default();
+        self.set_initial(Some(names), options)?;
+        self.initial = self.facts.snapshot();
+        self.session = Session::new(self.facts, &self.rules);
+        Ok(self.facts_to_json())
+    }
+
+    // Asserts ("A", "!A") or retracts facts of the session, the facts
+    // derived from them are solved again by the next query.
+    fn maintain(&mut self, body: &Value, retract: bool) -> Result<Value, Error> {
+        let mut invalidated = Vec::new();
+        for name in strings(body, "facts")?.unwrap_or_default() {
+            let (value, name) = match name.strip_prefix('!') {
+                Some(name) => (false, name),
+                None => (true, name),
+            };
+            for fact in self.facts.name_facts(name)? {
+                invalidated.append(&mut match retract {
+                    true => self.session.retract(fact),
+                    false => self.session.assert(fact, value),
+                });
+            }
+        }
+        invalidated.sort();
+        invalidated.dedup();
+        let invalidated = invalidated.iter().map(|name| Value::string(name)).collect();
+        Ok(Value::object(vec![(
+            "invalidated",
+            Value::Array(invalidated),
+        )]))
+    }
+
+    // Solves the queries of the body, "facts" replacing the facts of the
+    // session for this query only.
+    fn query(
+        &self,
+        body: &Value,
+        explain: bool,
+        template: &Template,
+        options: &Options,
+    ) -> Result<Value, Error> {
+        let names = strings(body, "facts")?;
+        if names.is_none() {
+            return self.solve(body, explain, template, options);
+        }
+        let saved = self.facts.snapshot();
+        let solved = self
+            .set_initial(names, options)
+            .and_then(|_| self.solve(body, explain, template, options));
+        self.facts.restore(&saved);
+        solved
+    }
+
+    fn solve(
+        &self,
+        body: &Value,
+        explain: bool,
+        template: &Template,
+        options: &Options,
+    ) -> Result<Value, Error> {
+        self.set_initial(None, options)?;
+        let mut expressions = Vec::new();
+        for query in strings(body, "queries")?.unwrap_or_default() {
+            let line = format!("?{}", query);
+            if lexer::is_expression(&line) {
+                expressions.push(Rules::expression(self.facts, &line, options)?);
+            } else {
+                self.facts.set_queries(&line, options)?;
+            }
+        }
+        let queries = expert_system::queries_of_parsed(self.facts);
+        if queries.is_empty() && expressions.is_empty() {
+            return Err(invalid("Serve: no queries provided".to_string()));
+        }
+        let solved = self.session.solve(queries, &self.rules, options)?;
+        let mut results: Vec<Value> = solved
+            .iter()
+            .map(|fact| {
+                Value::object(vec![
+                    ("query", Value::string(&fact.name)),
+                    ("state", state(fact)),
+                ])
+            })
+            .collect();
+        for expression in expressions.iter() {
+            let value = solver::solve_expression(expression, &self.rules, options)?;
+            results.push(Value::object(vec![
+                ("query", Value::string(&expression.label)),
+                ("state", Value::string(State::from_value(value).as_str())),
+            ]));
+        }
+        let mut response = vec![("results", Value::Array(results))];
+        if explain || body.get("explain").as_bool() == Some(true) {
+            let initial = self.session.asserted_facts();
+            let explainer = Explainer {
+                rules: &self.rules,
+                initial: &initial,
+                template,
+                options,
+            };
+            let queries = expert_system::queries_of_parsed(self.facts);
+            response.push(("explanation", Value::String(explainer.explain(&queries))));
+        }
+        Ok(Value::object(response))
+    }
+}
+
+pub struct Server {
+    bases: Vec<KnowledgeBase>,
+    template: Template,
+    options: Options,
+}
+
+fn error_body(message: &str) -> Value {
+    Value::object(vec![("error", Value::string(message))])
+}
+
+impl Server {
+    pub fn load(paths: &[&str], options: Options) -> Result<Self, Error> {
+        // Sessions solve facts as true, false or undetermined only.
+        let modes = [
+            ("--certainty", options.certainty),
+            ("--fuzzy", options.fuzzy.is_some()),
+            ("--probability", options.probability),
+        ];
+        if let Some((mode, _)) = modes.iter().find(|(_, set)| *set) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Serve: {} is not supported", mode),
+            ));
+        }
+        let mut bases: Vec<KnowledgeBase> = Vec::new();
+        for path in paths {
+            let base = KnowledgeBase::load(Path::new(path), &options)?;
+            if bases.iter().any(|b| b.name == base.name) {
+                return Err(invalid(format!("Serve: doublon (at {})", base.name)));
+            }
+            bases.push(base);
+        }
+        let template = match &options.template {
+            Some(path) => Template::load(path)?,
+            None => Template::default(),
+        };
+        Ok(Server {
+            bases,
+            template,
+            options,
+        })
+    }
+
+    fn list(&self) -> Value {
+        let bases = self
+            .bases
+            .iter()
+            .map(|base| {
+                Value::object(vec![
+                    ("name", Value::string(&base.name)),
+                    ("facts", Value::Number(base.facts.fact_arr.len() as f64)),
+                    ("rules", Value::Number(base.rules.iter().count() as f64)),
+                ])
+            })
+            .collect();
+        Value::object(vec![("knowledge_bases", Value::Array(bases))])
+    }
+
+    // Answers a request with its status and JSON body:
+    // GET /kb, GET /kb/{name}/facts, POST /kb/{name}/facts,
+    // GET /kb/{name}/rules, POST /kb/{name}/query, POST /kb/{name}/explain,
+    // POST /kb/{name}/assert and POST /kb/{name}/retract.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if parts == ["kb"] {
+            return match method {
+                "GET" => (200, self.list()),
+                _ => (405, error_body("Serve: method not allowed")),
+            };
+        }
+        let (name, endpoint) = match parts.as_slice() {
+            ["kb", name, endpoint] => (*name, *endpoint),
+            _ => return (404, error_body("Serve: not found")),
+        };
+        let body = match body.trim() {
+            "" => Value::Object(Vec::new()),
+            text => match Value::parse(text) {
+                Ok(body) => body,
+                Err(error) => return (400, error_body(&error.to_string())),
+            },
+        };
+        let (template, options) = (&self.template, &self.options);
+        let base = match self.bases.iter_mut().find(|base| base.name == name) {
+            Some(base) => base,
+            None => {
+                let message = format!("Serve: unknown knowledge base (at {})", name);
+                return (404, error_body(&message));
+            }
+        };
+        let result = match (method, endpoint) {
+            ("GET", "facts") => Ok(base.facts_to_json()),
+            ("POST", "facts") => base.post_facts(&body, options),
+            ("GET", "rules") => Ok(base.rules_to_json()),
+            ("POST", "query") => base.query(&body, false, template, options),
+            ("POST", "explain") => base.query(&body, true, template, options),
+            ("POST", "assert") => base.maintain(&body, false),
+            ("POST", "retract") => base.maintain(&body, true),
+            (_, "facts" | "rules" | "query" | "explain" | "assert" | "retract") => {
+                return (405, error_body("Serve: method not allowed"))
+            }
+            _ => return (404, error_body("Serve: not found")),
+        };
+        match result {
+            Ok(value) => (200, value),
+            Err(error) => (400, error_body(&error.to_string())),
+        }
+    }
+
+    fn respond(&mut self, mut stream: TcpStream) -> Result<(), Error> {
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut reader = BufReader::new(Deadline {
+            stream: stream.try_clone()?,
+            end: Instant::now() + TIMEOUT,
+        });
+        let (status, value) = match read_head(&mut reader)? {
+            Ok((method, path, length)) => {
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body)?;
+                self.handle(&method, &path, &String::from_utf8_lossy(&body))
+            }
+            Err(431) => (431, error_body("Serve: request header too large")),
+            Err(status) => (status, error_body("Serve: invalid Content-Length")),
+        };
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            _ => "Method Not Allowed",
+        };
+        let body = value.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+
+    // Serves the requests one at a time on localhost.
+    pub fn listen(&mut self, port: u16) -> Result<(), Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!(
+            "Serving {} knowledge base(s) on http://127.0.0.1:{}/kb",
+            self.bases.len(),
+            port
+        );
+        for stream in listener.incoming() {
+            if let Err(error) = stream.and_then(|stream| self.respond(stream)) {
+                eprintln!("serve: {}", error);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: &[String]) {
+    let mut options = Options::new();
+    let mut paths = Vec::new();
+    let usage = || println!("usage: ./expert_system serve [--port=port] input ...");
+    for arg in args.iter() {
+        match arg.chars().next() {
+            Some('-') => {
+                if let Err(error) = options.load(arg) {
+                    eprintln!("serve: {:?}", error.to_string());
+                    usage();
+                    std::process::exit(1);
+                }
+            }
+            _ => paths.push(arg.as_str()),
+        }
+    }
+    if paths.is_empty() {
+        usage();
+        return;
+    }
+    let port = options.port.unwrap_or(DEFAULT_PORT);
+    let served = Server::load(&paths, options).and_then(|mut server| server.listen(port));
+    if let Err(error) = served {
+        eprintln!("serve: {:?}", error.to_string());
+        std::process::exit(1);
+    }
+}
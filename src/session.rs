@@ -0,0 +1,128 @@
+use crate::facts::{Fact, Facts};
+use crate::options::Options;
+use crate::rules::Rules;
+use crate::solver;
+
+use std::io::Error;
+
+// A long lived solving session, in the style of a truth maintenance system:
+// derived facts stay solved between queries, and asserting or retracting a
+// fact only resets the facts derived from it.
+pub struct Session<'a> {
+    facts: &'a Facts,
+    // For each fact, the facts concluded by the rules reading it.
+    dependents: Vec<Vec<usize>>,
+    // Value of each asserted fact, None for the facts left to the rules.
+    asserted: Vec<Option<bool>>,
+}
+
+impl<'a> Session<'a> {
+    // Facts determined when the session starts are its asserted facts.
+    pub fn new(facts: &'a Facts, rules: &Rules) -> Self {
+        let index = |fact: &Fact| facts.fact_arr.iter().position(|f| f.name == fact.name);
+        let mut dependents = vec![Vec::new(); facts.fact_arr.len()];
+        for rule in rules.iter() {
+            let premises = rule.lhs.iter().filter_map(|t| t.fact).filter_map(index);
+            for premise in premises {
+                for conclusion in rule.rhs.iter().filter_map(|t| t.fact).filter_map(index) {
+                    if !dependents[premise].contains(&conclusion) {
+                        dependents[premise].push(conclusion);
+                    }
+                }
+            }
+        }
+        let asserted = facts
+            .fact_arr
+            .iter()
+            .map(|fact| fact.determined.get().then(|| fact.state.get()))
+            .collect();
+        Session {
+            facts,
+            dependents,
+            asserted,
+        }
+    }
+
+    fn index(&self, fact: &Fact) -> usize {
+        self.facts
+            .fact_arr
+            .iter()
+            .position(|f| f.name == fact.name)
+            .unwrap()
+    }
+
+    pub fn is_asserted(&self, fact: &Fact) -> bool {
+        self.asserted[self.index(fact)].is_some()
+    }
+
+    // Facts as asserted, derived ones forgotten, as the initial facts of an
+    // explanation.
+    pub fn asserted_facts(&self) -> Vec<Fact> {
+        let mut snapshot = self.facts.snapshot();
+        for (fact, asserted) in snapshot.iter_mut().zip(self.asserted.iter()) {
+            if asserted.is_none() {
+                forget(fact);
+            }
+        }
+        snapshot
+    }
+
+    // Forgets the value of every fact derived from the given one, and
+    // returns the names of those which were solved.
+    fn invalidate(&self, from: usize) -> Vec<String> {
+        let mut invalidated = Vec::new();
+        let mut seen = vec![false; self.dependents.len()];
+        let mut pending = self.dependents[from].clone();
+        while let Some(index) = pending.pop() {
+            if seen[index] || self.asserted[index].is_some() {
+                continue;
+            }
+            seen[index] = true;
+            let fact = &self.facts.fact_arr[index];
+            if fact.determined.get() || fact.state.get() {
+                invalidated.push(fact.name.clone());
+            }
+            forget(fact);
+            pending.extend(self.dependents[index].iter().copied());
+        }
+        invalidated.sort();
+        invalidated
+    }
+
+    // Asserts a fact, returning the derived facts to solve again.
+    pub fn assert(&mut self, fact: &Fact, value: bool) -> Vec<String> {
+        let index = self.index(fact);
+        self.asserted[index] = Some(value);
+        let fact = &self.facts.fact_arr[index];
+        forget(fact);
+        fact.state.set(value);
+        fact.determined.set(true);
+        self.invalidate(index)
+    }
+
+    // Retracts a fact, which is then left to the rules.
+    pub fn retract(&mut self, fact: &Fact) -> Vec<String> {
+        let index = self.index(fact);
+        self.asserted[index] = None;
+        forget(&self.facts.fact_arr[index]);
+        self.invalidate(index)
+    }
+
+    // Solves the queries, facts still valid from a previous query being
+    // kept as they are.
+    pub fn solve(
+        &self,
+        queries: Vec<&Fact>,
+        rules: &Rules,
+        options: &Options,
+    ) -> Result<Vec<Fact>, Error> {
+        solver::solve(queries, rules, options)
+    }
+}
+
+fn forget(fact: &Fact) {
+    fact.state.set(false);
+    fact.reverse_state.set(false);
+    fact.determined.set(false);
+    fact.rule.set(None);
+}
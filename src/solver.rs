@@ -1,11 +1,15 @@
 use std::io::{Error, ErrorKind};
 
+use crate::dot;
 use crate::facts::Fact;
 use crate::graph::{Graph, NodeIndex};
 use crate::options::Options;
 use crate::print;
 use crate::rules::{
-    rule::token::{Operand, Token},
+    rule::{
+        token::{Operand, Token},
+        Rule,
+    },
     Rules,
 };
 use crate::tree_builder;
@@ -18,37 +22,107 @@ fn get_plain_solved_queries(queries: Vec<&Fact>) -> Vec<Fact> {
     solved_queries
 }
 
-fn compute(operand: Operand, lhs: bool, rhs: bool) -> bool {
+// Kleene three-valued logic: None stands for an undetermined value.
+fn compute(operand: Operand, lhs: Option<bool>, rhs: Option<bool>) -> Option<bool> {
     match operand {
-        Operand::Not => !lhs,
-        Operand::And => lhs & rhs,
-        Operand::Or => lhs | rhs,
-        Operand::Xor => lhs ^ rhs,
+        Operand::Not => lhs.map(|lhs| !lhs),
+        Operand::And => match (lhs, rhs) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        Operand::Or => match (lhs, rhs) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        Operand::Xor => match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) => Some(lhs ^ rhs),
+            _ => None,
+        },
+        Operand::Nand => compute(Operand::And, lhs, rhs).map(|value| !value),
+        Operand::Nor => compute(Operand::Or, lhs, rhs).map(|value| !value),
+        Operand::Xnor => compute(Operand::Xor, lhs, rhs).map(|value| !value),
+        Operand::Imply => compute(Operand::Or, lhs.map(|lhs| !lhs), rhs),
+        Operand::Equiv => compute(Operand::Xnor, lhs, rhs),
         _ => panic!("Error: () in tree_solver()."),
     }
 }
 
-pub fn tree_solver(graph: &Graph<Token>, cur: NodeIndex) -> Result<bool, Error> {
+// Kleene "k of n": a bound holds or fails once undetermined values can't
+// change the count enough.
+fn cardinality(operand: Operand, values: &[Option<bool>]) -> Option<bool> {
+    let true_count = values.iter().filter(|v| **v == Some(true)).count();
+    let max_count = values.iter().filter(|v| **v != Some(false)).count();
+    match operand {
+        Operand::AtLeast(k) if true_count >= k as usize => Some(true),
+        Operand::AtLeast(k) if max_count < k as usize => Some(false),
+        Operand::AtMost(k) if max_count <= k as usize => Some(true),
+        Operand::AtMost(k) if true_count > k as usize => Some(false),
+        Operand::Exactly(k) => compute(
+            Operand::And,
+            cardinality(Operand::AtLeast(k), values),
+            cardinality(Operand::AtMost(k), values),
+        ),
+        _ => None,
+    }
+}
+
+fn list_values(
+    graph: &Graph<Token>,
+    cur: NodeIndex,
+    options: &Options,
+    values: &mut Vec<Option<bool>>,
+) -> Result<(), Error> {
+    match graph.get(cur) {
+        Some(node) if node.content.operand == Some(Operand::Comma) => {
+            for child in node.lhs.iter().chain(node.rhs.iter()) {
+                list_values(graph, *child, options, values)?;
+            }
+            Ok(())
+        }
+        _ => {
+            values.push(expr_solver(graph, cur, options)?);
+            Ok(())
+        }
+    }
+}
+
+pub fn fact_value(fact: &Fact, options: &Options) -> Option<bool> {
+    if fact.determined.get() || !options.open_world {
+        Some(fact.state.get())
+    } else {
+        None
+    }
+}
+
+fn expr_solver(
+    graph: &Graph<Token>,
+    cur: NodeIndex,
+    options: &Options,
+) -> Result<Option<bool>, Error> {
     match graph.get(cur) {
         Some(node) => {
             let token = node.content;
             if let Some(fact) = token.fact {
-                if fact.state.get() {
-                    return Ok(true);
-                }
-                if let Some(node_index) = node.lhs {
-                    return tree_solver(graph, node_index);
-                }
-                return Ok(false);
+                return Ok(fact_value(fact, options));
+            } else if token.constant.is_some() {
+                return Ok(token.constant);
             } else if let Some(op) = token.operand {
                 if let Some(lhs) = node.lhs {
                     if op == Operand::Not {
-                        return Ok(!tree_solver(graph, lhs)?);
+                        return Ok(compute(op, expr_solver(graph, lhs, options)?, None));
+                    } else if op.is_unary() {
+                        let mut values = Vec::new();
+                        list_values(graph, lhs, options, &mut values)?;
+                        return Ok(cardinality(op, &values));
                     } else if let Some(rhs) = node.rhs {
+                        // Rules are stored in reversed RPN, the right operand
+                        // of an operator is its first child.
                         return Ok(compute(
                             op,
-                            tree_solver(graph, lhs)?,
-                            tree_solver(graph, rhs)?,
+                            expr_solver(graph, rhs, options)?,
+                            expr_solver(graph, lhs, options)?,
                         ));
                     }
                 }
@@ -62,17 +136,70 @@ pub fn tree_solver(graph: &Graph<Token>, cur: NodeIndex) -> Result<bool, Error>
     }
 }
 
-pub fn solve(queries: Vec<&Fact>, rules: Rules, options: &Options) -> Result<Vec<Fact>, Error> {
+// Evaluates the rule body hanging under the queried fact node. Facts met in
+// the body are already solved, so their own subtrees are not walked again.
+pub fn tree_solver(
+    graph: &Graph<Token>,
+    cur: NodeIndex,
+    options: &Options,
+) -> Result<Option<bool>, Error> {
+    match graph.get(cur) {
+        Some(node) => {
+            if let Some(fact) = node.content.fact {
+                if fact.state.get() {
+                    return Ok(Some(true));
+                }
+                if let Some(node_index) = node.lhs {
+                    return expr_solver(graph, node_index, options);
+                }
+                return Ok(fact_value(fact, options));
+            }
+            expr_solver(graph, cur, options)
+        }
+        None => panic!("Error: print_tree_rec() out of bounds."),
+    }
+}
+
+pub fn solve_expression<'a>(
+    expression: &Rule<'a>,
+    rules: &'a Rules,
+    options: &Options,
+) -> Result<Option<bool>, Error> {
+    let graph = tree_builder::expression(&expression.lhs, rules, options)?;
+    if options.graph {
+        println!("=== GRAPH ===");
+        print::tree_to_file(&graph);
+    }
+    match graph.get(0).and_then(|root| root.lhs) {
+        Some(top) => expr_solver(&graph, top, options),
+        None => Err(Error::new(
+            ErrorKind::InvalidData,
+            "Tree solver: empty expression",
+        )),
+    }
+}
+
+pub fn solve(queries: Vec<&Fact>, rules: &Rules, options: &Options) -> Result<Vec<Fact>, Error> {
     for fact in queries.iter() {
         let mut graph: Graph<Token> = Graph::new();
-        let root: NodeIndex = graph.add_query(Token::new_fact(&fact));
+        let root: NodeIndex = graph.add_query(Token::new_fact(fact));
         if !fact.determined.get() {
-            graph = tree_builder::generate(graph, &rules, fact, root)?;
+            graph = tree_builder::generate(graph, rules, fact, root, options)?;
         }
         if options.graph {
             println!("=== GRAPH ===");
             print::tree_to_file(&graph);
         }
+        if let Some(dir) = &options.dot {
+            let name = format!("query_{}.dot", fact.name);
+            match dot::to_file(dir, &name, &dot::tree_to_dot(&graph, rules)) {
+                Ok(path) => println!(
+                    "The proof tree has been printed in the following file : {}",
+                    path.display()
+                ),
+                Err(error) => eprintln!("Error: {:?}", error.to_string()),
+            }
+        }
     }
     Ok(get_plain_solved_queries(queries))
 }
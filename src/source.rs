@@ -0,0 +1,257 @@
+use crate::lexer;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{prelude::*, BufReader, Error, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+pub struct Line {
+    pub file: Rc<String>,
+    pub module: Option<Rc<String>>,
+    pub number: usize,
+    pub text: String,
+}
+
+impl Line {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.number)
+    }
+
+    // "file:line:column" of a byte offset in the text, which holds one
+    // physical line per '\n' when a rule spans several lines.
+    pub fn position(&self, offset: usize) -> String {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line = self.number + before.matches('\n').count();
+        let column = match before.rfind('\n') {
+            Some(newline) => before[newline + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+        format!("{}:{}:{}", self.file, line, column)
+    }
+
+    pub fn error(&self, error: Error) -> Error {
+        let positioned = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<lexer::Positioned>());
+        match positioned {
+            Some(positioned) => Error::new(
+                error.kind(),
+                format!("{}: {}", self.position(positioned.offset), error),
+            ),
+            None => Error::new(error.kind(), format!("{}: {}", self.location(), error)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Source {
+    pub lines: Vec<Line>,
+    pub files: Vec<PathBuf>,
+    // Exported facts of each module, None when every fact is visible.
+    pub modules: BTreeMap<String, Option<BTreeSet<String>>>,
+    root_dir: PathBuf,
+    // Texts read instead of the files, by canonical path, for the buffers
+    // of an editor.
+    overlay: HashMap<PathBuf, String>,
+}
+
+fn include_path(text: &str) -> Option<Result<&str, Error>> {
+    let rest = lexer::keyword(text, "include")?;
+    if rest.len() < 3 || !rest.starts_with('"') || !rest.ends_with('"') {
+        return Some(Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Include: expected a quoted path (at {})", text.trim()),
+        )));
+    }
+    Some(Ok(&rest[1..rest.len() - 1]))
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => continue,
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+impl Source {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Source::load_overlaid(path, HashMap::new())
+    }
+
+    pub fn load_overlaid(path: &Path, overlay: HashMap<PathBuf, String>) -> Result<Self, Error> {
+        let mut source = Source {
+            root_dir: normalize(path.parent().unwrap_or_else(|| Path::new(""))),
+            overlay,
+            ..Default::default()
+        };
+        let mut stack = Vec::new();
+        source.load_file(path, &mut stack)?;
+        Ok(source)
+    }
+
+    // Path of a file from the name shown in locations.
+    pub fn path_of(&self, name: &str) -> PathBuf {
+        normalize(&self.root_dir.join(name))
+    }
+
+    pub fn display_name(&self, path: &Path) -> String {
+        let path = normalize(path);
+        let common = path
+            .components()
+            .zip(self.root_dir.components())
+            .take_while(|(lhs, rhs)| lhs == rhs)
+            .count();
+        let mut relative = PathBuf::new();
+        for _ in self.root_dir.components().skip(common) {
+            relative.push("..");
+        }
+        for component in path.components().skip(common) {
+            relative.push(component);
+        }
+        relative.display().to_string()
+    }
+
+    fn include(
+        &mut self,
+        path: &Path,
+        line: &Line,
+        included: &str,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        let included = path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(included);
+        let canonical = included.canonicalize().map_err(|e| {
+            line.error(Error::new(
+                e.kind(),
+                format!("Include: {}: {}", self.display_name(&included), e),
+            ))
+        })?;
+        if stack.contains(&canonical) {
+            return Err(line.error(Error::new(
+                ErrorKind::InvalidData,
+                format!("Include: cycle ({})", self.display_name(&included)),
+            )));
+        }
+        if !self.files.contains(&canonical) {
+            self.load_file(&included, stack)?;
+        }
+        Ok(())
+    }
+
+    fn declare_module(
+        &mut self,
+        name: &str,
+        module: &mut Option<Rc<String>>,
+        has_content: bool,
+    ) -> Result<(), Error> {
+        if module.is_some() || has_content {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Module: must be declared once, before any rule or fact",
+            ));
+        }
+        if !lexer::is_valid_name(name) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Module: invalid name ({})", name),
+            ));
+        }
+        self.modules.entry(name.to_string()).or_insert(None);
+        *module = Some(Rc::new(name.to_string()));
+        Ok(())
+    }
+
+    fn export(&mut self, names: &str, module: &Option<Rc<String>>) -> Result<(), Error> {
+        let module = match module {
+            Some(module) => module,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Export: only allowed inside a module",
+                ))
+            }
+        };
+        let exports = self
+            .modules
+            .entry(module.to_string())
+            .or_insert(None)
+            .get_or_insert_with(BTreeSet::new);
+        for name in names.split(|c: char| c == ',' || c.is_whitespace()) {
+            if name.is_empty() {
+                continue;
+            }
+            if !lexer::is_valid_name(name) || name.contains('.') {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Export: invalid fact name ({})", name),
+                ));
+            }
+            exports.insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    fn load_file(&mut self, path: &Path, stack: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let canonical = path.canonicalize()?;
+        let texts: Vec<String> = match self.overlay.get(&canonical) {
+            Some(text) => text.lines().map(String::from).collect(),
+            None => {
+                let mut texts = Vec::new();
+                for (i, text) in BufReader::new(File::open(path)?).lines().enumerate() {
+                    texts.push(text.map_err(|e| {
+                        Error::new(
+                            e.kind(),
+                            format!("{}:{}: {}", self.display_name(path), i + 1, e),
+                        )
+                    })?);
+                }
+                texts
+            }
+        };
+        let name = Rc::new(self.display_name(path));
+        let mut module = None;
+        let mut has_content = false;
+        stack.push(canonical.clone());
+        self.files.push(canonical);
+
+        for range in lexer::logical_lines(&texts) {
+            let mut line = Line {
+                file: Rc::clone(&name),
+                module: None,
+                number: range.start + 1,
+                text: lexer::join(&texts[range]).0,
+            };
+            if let Some(included) = include_path(&line.text) {
+                let included = included.map_err(|e| line.error(e))?;
+                self.include(path, &line, included, stack)?;
+            } else if let Some(name) = lexer::keyword(&line.text, "module") {
+                self.declare_module(name, &mut module, has_content)
+                    .map_err(|e| line.error(e))?;
+            } else if let Some(names) = lexer::keyword(&line.text, "export") {
+                self.export(names, &module).map_err(|e| line.error(e))?;
+            } else {
+                match line.text.trim().chars().next() {
+                    Some('#') | None => (),
+                    _ => has_content = true,
+                }
+                line.module = module.clone();
+                self.lines.push(line);
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+pub mod expression;
+pub mod tokens;
+
+use crate::lexer;
+use expression::Parser;
+use tokens::{tokenize, SyntaxToken, TokenKind};
+
+use std::fmt;
+
+// Byte offsets in the source, end excluded.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    File,
+    Blank,
+    Comment,
+    Directive,
+    Declaration,
+    Rule,
+    Label,
+    InitialFacts,
+    Queries,
+    Expectations,
+    Scenario,
+    Binary,
+    Unary,
+    Paren,
+    Cardinality,
+    Certainty,
+    Comparison,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span,
+            SyntaxElement::Token(token) => token.span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub children: Vec<SyntaxElement>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub message: String,
+}
+
+// Concrete syntax tree of a whole file: every char of the source is in one
+// of its tokens, whitespace and comments included.
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub errors: Vec<SyntaxError>,
+}
+
+impl SyntaxNode {
+    // An empty node sits at the given offset.
+    pub fn new(kind: NodeKind, children: Vec<SyntaxElement>, at: usize) -> Self {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => Span {
+                start: first.span().start,
+                end: last.span().end,
+            },
+            _ => Span { start: at, end: at },
+        };
+        SyntaxNode {
+            kind,
+            span,
+            children,
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    // Tokens of the node and of its descendants, in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children.iter() {
+            match child {
+                SyntaxElement::Node(node) => tokens.append(&mut node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text)?;
+        }
+        Ok(())
+    }
+}
+
+fn push_tokens(children: &mut Vec<SyntaxElement>, tokens: Vec<SyntaxToken>) {
+    children.extend(tokens.into_iter().map(SyntaxElement::Token));
+}
+
+// Splits the text before its comment and newline, which are trivia.
+fn directive(text: &str, keyword: &str, offset: usize) -> Vec<SyntaxElement> {
+    let mut children = Vec::new();
+    let indent = text.len() - text.trim_start().len();
+    let code = lexer::strip_comment(text).trim_end();
+    push_tokens(&mut children, tokenize(&text[..indent], offset));
+    let end = indent + keyword.len();
+    children.push(SyntaxElement::Token(SyntaxToken::new(
+        TokenKind::Keyword,
+        &text[indent..end],
+        offset + indent,
+    )));
+    let value = end + (code.len() - end) - code[end..].trim_start().len();
+    push_tokens(&mut children, tokenize(&text[end..value], offset + end));
+    if keyword == "include" {
+        if value < code.len() {
+            children.push(SyntaxElement::Token(SyntaxToken::new(
+                TokenKind::String,
+                &code[value..],
+                offset + value,
+            )));
+        }
+    } else {
+        push_tokens(&mut children, tokenize(&code[value..], offset + value));
+    }
+    push_tokens(
+        &mut children,
+        tokenize(&text[code.len()..], offset + code.len()),
+    );
+    children
+}
+
+fn declaration(text: &str, offset: usize) -> Vec<SyntaxElement> {
+    let mut children = Vec::new();
+    let colon = text.find(':').unwrap_or(text.len());
+    push_tokens(&mut children, tokenize(&text[..colon], offset));
+    children.push(SyntaxElement::Token(SyntaxToken::new(
+        TokenKind::Colon,
+        ":",
+        offset + colon,
+    )));
+    let code = lexer::strip_comment(text).trim_end().len().max(colon + 1);
+    let description = text[colon + 1..code].trim_start();
+    let start = code - description.len();
+    push_tokens(
+        &mut children,
+        tokenize(&text[colon + 1..start], offset + colon + 1),
+    );
+    if !description.is_empty() {
+        children.push(SyntaxElement::Token(SyntaxToken::new(
+            TokenKind::Text,
+            description,
+            offset + start,
+        )));
+    }
+    push_tokens(&mut children, tokenize(&text[code..], offset + code));
+    children
+}
+
+// First tokens of fact, query and scenario lines.
+fn prefixed(text: &str, offset: usize, prefix: &str, kind: TokenKind) -> Vec<SyntaxToken> {
+    let indent = text.len() - text.trim_start().len();
+    let end = indent + prefix.len();
+    let mut tokens = tokenize(&text[..indent], offset);
+    tokens.push(SyntaxToken::new(kind, &text[indent..end], offset + indent));
+    tokens.extend(tokenize(&text[end..], offset + end));
+    tokens
+}
+
+// "[0.8]", the certainty factor of a rule or an initial fact.
+fn certainty(parser: &mut Parser, children: &mut Vec<SyntaxElement>, at: usize) {
+    let mut factor = Vec::new();
+    parser.bump(&mut factor);
+    parser.trivia(&mut factor);
+    parser.number(&mut factor, "Syntax: expected a certainty factor");
+    parser.trivia(&mut factor);
+    parser.expect(TokenKind::RBracket, &mut factor, "Syntax: expected ]");
+    children.push(SyntaxElement::Node(SyntaxNode::new(
+        NodeKind::Certainty,
+        factor,
+        at,
+    )));
+}
+
+// Facts, queries and scenario names are words, '!' marks a negated fact.
+fn words(parser: &mut Parser, children: &mut Vec<SyntaxElement>, allowed: &[TokenKind]) {
+    parser.bump(children);
+    loop {
+        parser.trivia(children);
+        match parser.peek() {
+            Some(kind) if allowed.contains(&kind) => parser.bump(children),
+            Some(TokenKind::LBracket) if allowed.contains(&TokenKind::Not) => {
+                certainty(parser, children, 0)
+            }
+            Some(TokenKind::Equals) if allowed.contains(&TokenKind::Not) => {
+                parser.bump(children);
+                parser.trivia(children);
+                parser.number(children, "Syntax: expected a value");
+            }
+            _ => break,
+        }
+    }
+    parser.rest(children, true);
+}
+
+fn rule(parser: &mut Parser, children: &mut Vec<SyntaxElement>, at: usize) {
+    parser.trivia(children);
+    if parser.peek() == Some(TokenKind::LBracket) {
+        let mut label = Vec::new();
+        parser.bump(&mut label);
+        parser.trivia(&mut label);
+        parser.expect(TokenKind::Name, &mut label, "Syntax: expected a label");
+        parser.trivia(&mut label);
+        parser.expect(TokenKind::RBracket, &mut label, "Syntax: expected ]");
+        children.push(SyntaxElement::Node(SyntaxNode::new(
+            NodeKind::Label,
+            label,
+            at,
+        )));
+        parser.trivia(children);
+    }
+    children.push(parser.expression(false));
+    parser.trivia(children);
+    match parser.peek() {
+        Some(TokenKind::Imply) | Some(TokenKind::Equiv) => parser.bump(children),
+        _ => {
+            parser.error("Syntax: expected => or <=>");
+            return parser.rest(children, false);
+        }
+    }
+    parser.trivia(children);
+    children.push(parser.expression(false));
+    parser.trivia(children);
+    if parser.peek() == Some(TokenKind::LBracket) {
+        certainty(parser, children, at);
+    }
+    parser.rest(children, true);
+}
+
+fn keyword(text: &str) -> Option<&'static str> {
+    ["include", "module", "export"]
+        .iter()
+        .copied()
+        .find(|keyword| lexer::keyword(text, keyword).is_some())
+}
+
+fn parse_line(text: &str, offset: usize, errors: &mut Vec<SyntaxError>) -> SyntaxNode {
+    let trimmed = text.trim();
+    let mut children = Vec::new();
+    if let Some(keyword) = keyword(text) {
+        return SyntaxNode::new(
+            NodeKind::Directive,
+            directive(text, keyword, offset),
+            offset,
+        );
+    }
+    if lexer::declaration(text).is_some() {
+        return SyntaxNode::new(NodeKind::Declaration, declaration(text, offset), offset);
+    }
+    let (kind, tokens) = match trimmed.chars().next() {
+        None => (NodeKind::Blank, tokenize(text, offset)),
+        Some('#') => (NodeKind::Comment, tokenize(text, offset)),
+        Some(c) if lexer::is_rule_start(c) => (NodeKind::Rule, tokenize(text, offset)),
+        Some('=') => (
+            NodeKind::InitialFacts,
+            prefixed(text, offset, "=", TokenKind::Equals),
+        ),
+        Some('?') if trimmed.starts_with("?=") => (
+            NodeKind::Expectations,
+            prefixed(text, offset, "?=", TokenKind::QuestionEquals),
+        ),
+        Some('?') => (
+            NodeKind::Queries,
+            prefixed(text, offset, "?", TokenKind::Question),
+        ),
+        Some('@') => (
+            NodeKind::Scenario,
+            prefixed(text, offset, "@", TokenKind::At),
+        ),
+        Some(_) => (NodeKind::Error, tokenize(text, offset)),
+    };
+    let mut parser = Parser::new(tokens, offset + text.len(), errors);
+    match kind {
+        NodeKind::Rule => rule(&mut parser, &mut children, offset),
+        NodeKind::InitialFacts => {
+            parser.trivia(&mut children);
+            words(
+                &mut parser,
+                &mut children,
+                &[TokenKind::Name, TokenKind::Not],
+            );
+        }
+        NodeKind::Queries if lexer::is_expression(text) => {
+            parser.trivia(&mut children);
+            parser.bump(&mut children);
+            parser.trivia(&mut children);
+            children.push(parser.expression(false));
+            parser.rest(&mut children, true);
+        }
+        NodeKind::Queries | NodeKind::Expectations | NodeKind::Scenario => {
+            parser.trivia(&mut children);
+            words(&mut parser, &mut children, &[TokenKind::Name]);
+        }
+        _ => parser.rest(&mut children, true),
+    }
+    SyntaxNode::new(kind, children, offset)
+}
+
+// Parses a whole file, a rule going on over several lines being one node.
+// Errors are collected rather than raised, the tree always holds the full
+// source.
+pub fn parse(text: &str) -> SyntaxTree {
+    let mut errors = Vec::new();
+    let physical: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for range in lexer::logical_lines(&physical) {
+        let end = start + physical[range].iter().map(|line| line.len()).sum::<usize>();
+        lines.push(SyntaxElement::Node(parse_line(
+            &text[start..end],
+            start,
+            &mut errors,
+        )));
+        start = end;
+    }
+    SyntaxTree {
+        root: SyntaxNode::new(NodeKind::File, lines, 0),
+        errors,
+    }
+}
@@ -0,0 +1,234 @@
+use crate::rules::rule::{rpn, token::Operand};
+
+use super::tokens::{SyntaxToken, TokenKind};
+use super::{NodeKind, SyntaxElement, SyntaxError, SyntaxNode};
+
+// Loosest priority an expression can hold, commas aside.
+const LOOSEST: u8 = 6;
+
+// Recursive descent over the tokens of one line. Every function expects the
+// trivia before its first token already taken, and leaves the trivia after
+// its last token to the caller, so that nothing of the source is dropped.
+pub struct Parser<'a> {
+    tokens: Vec<SyntaxToken>,
+    pos: usize,
+    // Offset where the tokens end, for the span of an empty node.
+    end: usize,
+    pub errors: &'a mut Vec<SyntaxError>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<SyntaxToken>, end: usize, errors: &'a mut Vec<SyntaxError>) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            end,
+            errors,
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |t| t.span.start)
+    }
+
+    fn next(&self) -> Option<&SyntaxToken> {
+        self.tokens[self.pos..].iter().find(|t| !t.kind.is_trivia())
+    }
+
+    pub fn peek(&self) -> Option<TokenKind> {
+        self.next().map(|t| t.kind)
+    }
+
+    // Whether the name about to be read is compared, "temperature > 38".
+    fn compares(&self) -> bool {
+        self.tokens[self.pos..]
+            .iter()
+            .skip(1)
+            .find(|t| !t.kind.is_trivia())
+            .is_some_and(|t| t.kind == TokenKind::Compare)
+    }
+
+    pub fn trivia(&mut self, children: &mut Vec<SyntaxElement>) {
+        while let Some(token) = self.tokens.get(self.pos) {
+            if !token.kind.is_trivia() {
+                break;
+            }
+            children.push(SyntaxElement::Token(token.clone()));
+            self.pos += 1;
+        }
+    }
+
+    pub fn bump(&mut self, children: &mut Vec<SyntaxElement>) {
+        if let Some(token) = self.tokens.get(self.pos) {
+            children.push(SyntaxElement::Token(token.clone()));
+            self.pos += 1;
+        }
+    }
+
+    // Reported on the next token, or at the end of the line.
+    pub fn error(&mut self, message: &str) {
+        let span = self.next().map_or(
+            super::Span {
+                start: self.end,
+                end: self.end,
+            },
+            |t| t.span,
+        );
+        self.errors.push(SyntaxError {
+            span,
+            message: message.to_string(),
+        });
+    }
+
+    // Takes the expected token, or reports it missing.
+    pub fn expect(&mut self, kind: TokenKind, children: &mut Vec<SyntaxElement>, message: &str) {
+        if self.peek() == Some(kind) {
+            self.bump(children);
+        } else {
+            self.error(message);
+        }
+    }
+
+    // Whatever is left on the line is kept in an error node, reported
+    // unless an error was already raised for it.
+    pub fn rest(&mut self, children: &mut Vec<SyntaxElement>, report: bool) {
+        self.trivia(children);
+        if self.pos >= self.tokens.len() {
+            return;
+        }
+        if report {
+            self.error("Syntax: unexpected token");
+        }
+        let mut rest = Vec::new();
+        let mut trailing = Vec::new();
+        while let Some(token) = self.tokens.get(self.pos) {
+            if token.kind.is_trivia() {
+                trailing.push(SyntaxElement::Token(token.clone()));
+            } else {
+                rest.append(&mut trailing);
+                rest.push(SyntaxElement::Token(token.clone()));
+            }
+            self.pos += 1;
+        }
+        children.push(SyntaxElement::Node(SyntaxNode::new(
+            NodeKind::Error,
+            rest,
+            self.end,
+        )));
+        children.append(&mut trailing);
+    }
+
+    // Takes a number, 0 and 1 being read as numbers rather than constants.
+    pub fn number(&mut self, children: &mut Vec<SyntaxElement>, message: &str) {
+        match self.peek() {
+            Some(TokenKind::Number) | Some(TokenKind::Constant) => {
+                self.tokens[self.pos].kind = TokenKind::Number;
+                self.bump(children);
+            }
+            _ => self.error(message),
+        }
+    }
+
+    // Expression of a rule side or a query, nested says whether an
+    // implication can be met outside brackets.
+    pub fn expression(&mut self, nested: bool) -> SyntaxElement {
+        self.binary(LOOSEST, nested)
+    }
+
+    fn binary(&mut self, limit: u8, nested: bool) -> SyntaxElement {
+        let mut lhs = self.unary();
+        loop {
+            let op = match self.peek().and_then(|kind| kind.binary_operand()) {
+                Some(op) => op,
+                None => return lhs,
+            };
+            let priority = rpn::op_priority(op);
+            let is_impliance = op == Operand::Imply || op == Operand::Equiv;
+            if priority > limit || (is_impliance && !nested) {
+                return lhs;
+            }
+            let at = lhs.span().start;
+            let mut children = vec![lhs];
+            self.trivia(&mut children);
+            self.bump(&mut children);
+            self.trivia(&mut children);
+            // Same priorities are read from the left, but for implications.
+            let rhs_limit = if op == Operand::Imply {
+                priority
+            } else {
+                priority - 1
+            };
+            children.push(self.binary(rhs_limit, nested));
+            lhs = SyntaxElement::Node(SyntaxNode::new(NodeKind::Binary, children, at));
+        }
+    }
+
+    fn unary(&mut self) -> SyntaxElement {
+        let at = self.offset();
+        let mut children = Vec::new();
+        let kind = match self.peek() {
+            Some(TokenKind::Name) if self.compares() => {
+                self.bump(&mut children);
+                self.trivia(&mut children);
+                self.bump(&mut children);
+                self.trivia(&mut children);
+                self.number(&mut children, "Syntax: expected a number");
+                NodeKind::Comparison
+            }
+            Some(TokenKind::Name) | Some(TokenKind::Constant) => {
+                self.bump(&mut children);
+                return children.pop().unwrap();
+            }
+            Some(TokenKind::Not) => {
+                self.bump(&mut children);
+                self.trivia(&mut children);
+                children.push(self.unary());
+                NodeKind::Unary
+            }
+            Some(TokenKind::LParen) => {
+                self.bump(&mut children);
+                self.trivia(&mut children);
+                children.push(self.expression(true));
+                self.trivia(&mut children);
+                self.expect(TokenKind::RParen, &mut children, "Syntax: unclosed bracket");
+                NodeKind::Paren
+            }
+            Some(TokenKind::Keyword) => {
+                self.cardinality(&mut children);
+                NodeKind::Cardinality
+            }
+            Some(TokenKind::RParen | TokenKind::Comma | TokenKind::Imply | TokenKind::Equiv)
+            | None => {
+                self.error("Syntax: missing operand");
+                NodeKind::Error
+            }
+            Some(_) => {
+                self.error("Syntax: unexpected token");
+                self.bump(&mut children);
+                NodeKind::Error
+            }
+        };
+        SyntaxElement::Node(SyntaxNode::new(kind, children, at))
+    }
+
+    // atleast(k, expression, ...)
+    fn cardinality(&mut self, children: &mut Vec<SyntaxElement>) {
+        self.bump(children);
+        self.trivia(children);
+        self.expect(TokenKind::LParen, children, "Syntax: expected (");
+        self.trivia(children);
+        self.number(children, "Syntax: expected a threshold");
+        self.trivia(children);
+        self.expect(TokenKind::Comma, children, "Syntax: expected ,");
+        loop {
+            self.trivia(children);
+            children.push(self.expression(true));
+            self.trivia(children);
+            match self.peek() {
+                Some(TokenKind::Comma) => self.bump(children),
+                _ => break,
+            }
+        }
+        self.expect(TokenKind::RParen, children, "Syntax: unclosed bracket");
+    }
+}
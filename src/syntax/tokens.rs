@@ -0,0 +1,164 @@
+use crate::lexer;
+use crate::rules::rule::token::Operand;
+
+use super::Span;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Whitespace,
+    Newline,
+    Comment,
+    Name,
+    Constant,
+    Number,
+    // include, module, export and the cardinalities.
+    Keyword,
+    String,
+    Text,
+    Not,
+    And,
+    Or,
+    Xor,
+    Nand,
+    Nor,
+    Xnor,
+    Imply,
+    Equiv,
+    // >, >=, <, <=, == and != after a numeric fact.
+    Compare,
+    LParen,
+    RParen,
+    Comma,
+    LBracket,
+    RBracket,
+    Colon,
+    Equals,
+    Question,
+    QuestionEquals,
+    At,
+    Error,
+}
+
+impl TokenKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace | TokenKind::Newline | TokenKind::Comment
+        )
+    }
+
+    // Operator of a binary expression, to weigh it with rpn::op_priority.
+    pub fn binary_operand(self) -> Option<Operand> {
+        match self {
+            TokenKind::And => Some(Operand::And),
+            TokenKind::Or => Some(Operand::Or),
+            TokenKind::Xor => Some(Operand::Xor),
+            TokenKind::Nand => Some(Operand::Nand),
+            TokenKind::Nor => Some(Operand::Nor),
+            TokenKind::Xnor => Some(Operand::Xnor),
+            TokenKind::Imply => Some(Operand::Imply),
+            TokenKind::Equiv => Some(Operand::Equiv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub span: Span,
+    pub text: String,
+}
+
+impl SyntaxToken {
+    pub fn new(kind: TokenKind, text: &str, offset: usize) -> Self {
+        SyntaxToken {
+            kind,
+            span: Span {
+                start: offset,
+                end: offset + text.len(),
+            },
+            text: text.to_string(),
+        }
+    }
+}
+
+fn token_len(text: &str) -> (TokenKind, usize) {
+    let mut chars = text.chars();
+    let c = match chars.next() {
+        Some(c) => c,
+        None => return (TokenKind::Error, 0),
+    };
+    let next = chars.next();
+    let run = |f: fn(char) -> bool| text.find(|c| !f(c)).unwrap_or(text.len());
+    match c {
+        '\n' => (TokenKind::Newline, 1),
+        c if c.is_whitespace() => (
+            TokenKind::Whitespace,
+            run(|c| c.is_whitespace() && c != '\n'),
+        ),
+        '#' => (TokenKind::Comment, text.find('\n').unwrap_or(text.len())),
+        c if lexer::is_name_start(c) => {
+            let len = run(lexer::is_name_char);
+            let kind = match &text[..len] {
+                "TRUE" | "FALSE" => TokenKind::Constant,
+                name if lexer::is_cardinality(name) && text[len..].starts_with('(') => {
+                    TokenKind::Keyword
+                }
+                _ => TokenKind::Name,
+            };
+            (kind, len)
+        }
+        '-' if next.is_some_and(|c| c.is_ascii_digit()) => (
+            TokenKind::Number,
+            1 + text[1..]
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(text.len() - 1),
+        ),
+        c if c.is_ascii_digit() => {
+            // Certainty factors are decimal numbers, "[0.8]".
+            let len = run(|c| c.is_ascii_digit() || c == '.');
+            let is_constant = len == 1 && c <= '1' && !text[1..].starts_with(lexer::is_name_char);
+            if is_constant {
+                (TokenKind::Constant, 1)
+            } else {
+                (TokenKind::Number, len)
+            }
+        }
+        '!' => match next {
+            Some('+') => (TokenKind::Nand, 2),
+            Some('|') => (TokenKind::Nor, 2),
+            Some('^') => (TokenKind::Xnor, 2),
+            Some('=') => (TokenKind::Compare, 2),
+            _ => (TokenKind::Not, 1),
+        },
+        '=' if next == Some('>') => (TokenKind::Imply, 2),
+        '<' if text.starts_with("<=>") => (TokenKind::Equiv, 3),
+        '=' | '<' | '>' if next == Some('=') => (TokenKind::Compare, 2),
+        '<' | '>' => (TokenKind::Compare, 1),
+        // Value of a numeric fact, "temperature = 39.2".
+        '=' => (TokenKind::Equals, 1),
+        '+' => (TokenKind::And, 1),
+        '|' => (TokenKind::Or, 1),
+        '^' => (TokenKind::Xor, 1),
+        '(' => (TokenKind::LParen, 1),
+        ')' => (TokenKind::RParen, 1),
+        ',' => (TokenKind::Comma, 1),
+        '[' => (TokenKind::LBracket, 1),
+        ']' => (TokenKind::RBracket, 1),
+        c => (TokenKind::Error, c.len_utf8()),
+    }
+}
+
+// Splits the text of rules, fact and query lines, offset being the
+// position of the text in the file.
+pub fn tokenize(text: &str, offset: usize) -> Vec<SyntaxToken> {
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    while at < text.len() {
+        let (kind, len) = token_len(&text[at..]);
+        tokens.push(SyntaxToken::new(kind, &text[at..at + len], offset + at));
+        at += len;
+    }
+    tokens
+}
@@ -1,6 +1,7 @@
 use crate::checker;
 use crate::facts::Fact;
 use crate::graph::{Graph, NodeIndex};
+use crate::options::Options;
 use crate::rules::{
     rule::{
         token::{Operand, Token},
@@ -21,7 +22,6 @@ fn push_operand<'a>(
     match side {
         Side::Lhs => *cur = graph.insert_lhs(*cur, token)?,
         Side::Rhs => *cur = graph.insert_rhs(*cur, token)?,
-        _ => panic!("Only lhs/rhs can be pushed in a graph. Code error"),
     }
     Ok(graph)
 }
@@ -33,22 +33,17 @@ fn push_fact<'a>(
     fact: &'a Fact,
     cur: &mut NodeIndex,
     side: Side,
+    options: &Options,
 ) -> Result<Graph<Token<'a>>, Error> {
     let sub_head = match side {
         Side::Lhs => graph.insert_lhs(*cur, token)?,
         Side::Rhs => graph.insert_rhs(*cur, token)?,
-        _ => panic!("Only lhs/rhs can be pushed in a graph. Code error"),
     };
     if !fact.determined.get() {
         match checker::infinite_rule_loop(&graph, sub_head, fact) {
-            Ok(()) => graph = generate(graph, rules, fact, sub_head)?,
-            Err(e) => {
-                if e.kind() == ErrorKind::NotFound {
-                    println!("WTF, {:?}", e);
-                    return Err(e);
-                }
-                fact.state.set(false);
-            }
+            Ok(()) => graph = generate(graph, rules, fact, sub_head, options)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(e),
+            Err(_) => fact.state.set(false),
         }
     }
     Ok(graph)
@@ -59,6 +54,7 @@ fn push_rec<'a>(
     rules: &'a Rules,
     token: Token<'a>,
     cur: &mut NodeIndex,
+    options: &Options,
 ) -> Result<Graph<Token<'a>>, Error> {
     match graph.get(*cur) {
         Some(mut node) => {
@@ -66,19 +62,27 @@ fn push_rec<'a>(
                 if token.is_operand() {
                     graph = push_operand(graph, token, cur, Side::Lhs)?;
                 } else if let Some(fact) = token.fact {
-                    graph = push_fact(graph, rules, token, fact, cur, Side::Lhs)?;
+                    graph = push_fact(graph, rules, token, fact, cur, Side::Lhs, options)?;
+                } else if token.constant.is_some() {
+                    graph.insert_lhs(*cur, token)?;
                 }
-            } else if token.operand.is_some() && node.rhs.is_none() {
+            } else if token.operand.is_some()
+                && node.rhs.is_none()
+                && !node.content.operand.is_some_and(Operand::is_unary)
+            {
                 graph = push_operand(graph, token, cur, Side::Rhs)?;
             } else {
                 while node.parent.is_some() {
-                    if node.rhs.is_some() || node.content.operand == Some(Operand::Not) {
+                    if node.rhs.is_some() || node.content.operand.is_some_and(Operand::is_unary) {
                         *cur = node.parent.unwrap();
                     } else if token.is_operand() {
                         graph = push_operand(graph, token, cur, Side::Rhs)?;
                         break;
                     } else if let Some(fact) = token.fact {
-                        graph = push_fact(graph, rules, token, fact, cur, Side::Rhs)?;
+                        graph = push_fact(graph, rules, token, fact, cur, Side::Rhs, options)?;
+                        break;
+                    } else if token.constant.is_some() {
+                        graph.insert_rhs(*cur, token)?;
                         break;
                     }
                     if let Some(tmp) = graph.get(*cur) {
@@ -100,32 +104,50 @@ fn push_rec<'a>(
     }
 }
 
+// Builds the tree of an expression query under an empty root, solving the
+// facts met on the way.
+pub fn expression<'a>(
+    tokens: &[Token<'a>],
+    rules: &'a Rules,
+    options: &Options,
+) -> Result<Graph<Token<'a>>, Error> {
+    let mut graph = Graph::new();
+    let mut cur = graph.add_query(Token::new(None, None));
+    for token in tokens.iter() {
+        graph = push_rec(graph, rules, *token, &mut cur, options)?;
+    }
+    Ok(graph)
+}
+
 pub fn generate<'a>(
     mut graph: Graph<Token<'a>>,
     rules: &'a Rules,
     queried: &'a Fact,
     mut cur: NodeIndex,
+    options: &Options,
 ) -> Result<Graph<Token<'a>>, Error> {
     let saved_len = cur;
-    for rule in rules.iter() {
+    let mut fired = false;
+    for (i, rule) in rules.iter().enumerate() {
         if rule.implies_fact(queried) {
-            if let Some(mut false_node) = graph.get_mut(saved_len) {
+            if let Some(false_node) = graph.get_mut(saved_len) {
                 false_node.lhs = None;
                 graph.truncate(saved_len + 1);
                 cur = saved_len;
             }
             for token in rule.lhs.iter() {
                 let token = *token;
-                graph = push_rec(graph, rules, token, &mut cur)?;
+                graph = push_rec(graph, rules, token, &mut cur, options)?;
             }
-            let solved_branch = solver::tree_solver(&graph, saved_len)?;
-            if solved_branch {
-                queried.set_solved(solved_branch);
+            if solver::tree_solver(&graph, saved_len, options)? == Some(true) {
+                queried.set_solved(true);
+                queried.rule.set(Some(i));
+                fired = true;
                 break;
             }
         }
     }
-    if !queried.determined.get() {
+    if !queried.determined.get() && (fired || !options.open_world) {
         queried.determined.set(true);
     }
     Ok(graph)
@@ -0,0 +1,121 @@
+use crate::diff;
+use crate::options::Options;
+use crate::print;
+use crate::regression;
+use crate::source::Source;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL: Duration = Duration::from_millis(300);
+
+// Modification time and length of a file, None while it can't be read.
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// The input file and everything it includes, a change of any of them
+// triggering a new run. Only their metadata is polled, the includes being
+// loaded again once one of them changed.
+pub struct Watcher {
+    root: PathBuf,
+    snapshot: Vec<(PathBuf, Stamp)>,
+}
+
+impl Watcher {
+    pub fn new(root: &Path) -> Self {
+        let mut watcher = Watcher {
+            root: root.to_path_buf(),
+            snapshot: Vec::new(),
+        };
+        watcher.snapshot = watcher.take_snapshot();
+        watcher
+    }
+
+    // Files of the last good load are kept while an include is broken, so
+    // that fixing it is noticed.
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = match Source::load(&self.root) {
+            Ok(source) => source.files,
+            Err(_) => self.snapshot.iter().map(|(path, _)| path.clone()).collect(),
+        };
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        if !files.contains(&root) {
+            files.insert(0, root);
+        }
+        files
+    }
+
+    fn take_snapshot(&self) -> Vec<(PathBuf, Stamp)> {
+        self.files()
+            .into_iter()
+            .map(|path| {
+                let stamp = stamp(&path);
+                (path, stamp)
+            })
+            .collect()
+    }
+
+    pub fn files_watched(&self) -> usize {
+        self.snapshot.len()
+    }
+
+    // Whether a watched file changed since the last call.
+    pub fn changed(&mut self) -> bool {
+        if self
+            .snapshot
+            .iter()
+            .all(|(path, previous)| stamp(path) == *previous)
+        {
+            return false;
+        }
+        self.snapshot = self.take_snapshot();
+        true
+    }
+}
+
+// Output of a run followed by what changed since the previous one.
+pub fn report(previous: Option<&str>, current: &str, run: usize) -> String {
+    let mut out = format!("=== run {} ===\n{}", run, current);
+    match previous {
+        None => (),
+        Some(previous) if previous == current => {
+            out.push_str("No change since the previous run.\n")
+        }
+        Some(previous) => {
+            out.push_str("Changes since the previous run:\n");
+            out.push_str(&diff::unified(previous, current, "previous", "current"));
+        }
+    }
+    out
+}
+
+pub fn run(filename: &str, options: &Options) {
+    let path = Path::new(filename);
+    let mut watcher = Watcher::new(path);
+    let mut previous: Option<String> = None;
+    let mut run = 1;
+    loop {
+        let current = regression::actual_output(path, options)
+            .unwrap_or_else(|error| print::error_to_string(&error));
+        print!("{}", report(previous.as_deref(), &current, run));
+        println!(
+            "Watching {} file(s) of {}, Ctrl-C to stop",
+            watcher.files_watched(),
+            filename
+        );
+        previous = Some(current);
+        run += 1;
+        while !watcher.changed() {
+            thread::sleep(POLL);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::facts::{Fact, Measure, State};
+use crate::options::Options;
+use crate::rules::{
+    rule::{
+        side_to_infix,
+        token::{Operand, Token},
+    },
+    Rules,
+};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+// Values of a weighted mode: certainty factors or truth degrees.
+pub trait Algebra {
+    // Name of the values in explanations.
+    fn name(&self) -> &'static str;
+    fn measure(&self, value: f64) -> Measure;
+    fn state(&self, value: f64, options: &Options) -> State;
+    // Value of an initial fact.
+    fn initial(&self, fact: &Fact) -> f64;
+    // Value of TRUE or FALSE, that of a fact no rule concludes being zero.
+    fn truth(&self, value: bool) -> f64;
+    fn not(&self, value: f64) -> f64;
+    fn compute(&self, operand: Operand, lhs: f64, rhs: f64) -> f64;
+    // Contribution of a rule whose premise has the value given, None if it
+    // does not fire.
+    fn fire(&self, premise: f64, strength: f64) -> Option<f64>;
+    // Combination of two contributions to a same conclusion.
+    fn combine(&self, x: f64, y: f64) -> f64;
+    // Value of a fact from the contributions to it and to its negation.
+    fn conclude(&self, positive: f64, negative: f64) -> f64;
+}
+
+// State told by a value, those from `low` to `high` being undetermined in
+// an open world and false otherwise.
+pub fn state(value: f64, low: f64, high: f64, options: &Options) -> State {
+    if value > high {
+        State::True
+    } else if value < low || !options.open_world {
+        State::False
+    } else {
+        State::Undetermined
+    }
+}
+
+// Value in [0, 1] of an initial fact, "!A [0.7]" being A false to 0.7.
+pub fn degree(fact: &Fact) -> f64 {
+    match fact.certainty.get() {
+        Some(factor) if fact.state.get() => factor,
+        Some(factor) => 1.0 - factor.abs(),
+        None if fact.state.get() => 1.0,
+        None => 0.0,
+    }
+}
+
+pub fn round(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+// "At least k" holds as much as the k-th greatest value.
+fn cardinality(algebra: &dyn Algebra, operand: Operand, values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| b.partial_cmp(a).unwrap());
+    let at_least = |k: usize| match k {
+        0 => algebra.truth(true),
+        k => values
+            .get(k - 1)
+            .copied()
+            .unwrap_or_else(|| algebra.truth(false)),
+    };
+    match operand {
+        Operand::AtLeast(k) => at_least(k as usize),
+        Operand::AtMost(k) => algebra.not(at_least(k as usize + 1)),
+        Operand::Exactly(k) => at_least(k as usize).min(algebra.not(at_least(k as usize + 1))),
+        _ => 0.0,
+    }
+}
+
+// How each value was computed, in the order they were.
+#[derive(Default)]
+pub struct Trace(RefCell<Vec<String>>);
+
+impl Trace {
+    pub fn note(&self, line: String) {
+        self.0.borrow_mut().push(line);
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    pub fn explanation(&self) -> String {
+        let mut out = String::new();
+        for line in self.0.borrow().iter() {
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// Values of the facts, solved backward from the queries through every rule
+// concluding them.
+pub struct Weighted<'a> {
+    rules: &'a Rules<'a>,
+    options: &'a Options,
+    algebra: &'a dyn Algebra,
+    known: RefCell<HashMap<String, f64>>,
+    solving: RefCell<HashSet<String>>,
+    pub trace: Trace,
+}
+
+impl<'a> Weighted<'a> {
+    pub fn new(rules: &'a Rules<'a>, options: &'a Options, algebra: &'a dyn Algebra) -> Self {
+        Weighted {
+            rules,
+            options,
+            algebra,
+            known: RefCell::new(HashMap::new()),
+            solving: RefCell::new(HashSet::new()),
+            trace: Trace::default(),
+        }
+    }
+
+    // Values of the list of a cardinality, chained by commas.
+    fn values(&self, tokens: &[Token], i: &mut usize, values: &mut Vec<f64>) {
+        if tokens.get(*i).and_then(|t| t.operand) == Some(Operand::Comma) {
+            *i += 1;
+            self.values(tokens, i, values);
+            self.values(tokens, i, values);
+        } else {
+            values.push(self.eval(tokens, i));
+        }
+    }
+
+    // Rules are stored in reversed RPN, an operator is followed by its right
+    // operand, then its left one.
+    fn eval(&self, tokens: &[Token], i: &mut usize) -> f64 {
+        let token = match tokens.get(*i) {
+            Some(token) => *token,
+            None => return 0.0,
+        };
+        *i += 1;
+        if let Some(fact) = token.fact {
+            return self.fact(fact);
+        }
+        if let Some(constant) = token.constant {
+            return self.algebra.truth(constant);
+        }
+        match token.operand {
+            Some(Operand::Not) => self.algebra.not(self.eval(tokens, i)),
+            Some(op) if op.is_unary() => {
+                let mut values = Vec::new();
+                self.values(tokens, i, &mut values);
+                cardinality(self.algebra, op, &mut values)
+            }
+            Some(op) => {
+                let rhs = self.eval(tokens, i);
+                let lhs = self.eval(tokens, i);
+                self.algebra.compute(op, lhs, rhs)
+            }
+            None => 0.0,
+        }
+    }
+
+    // Value of an expression, with the state it tells.
+    pub fn expression(&self, tokens: &[Token]) -> (State, Measure) {
+        let value = self.eval(tokens, &mut 0);
+        (
+            self.algebra.state(value, self.options),
+            self.algebra.measure(value),
+        )
+    }
+
+    // Forgets the values of the facts, to solve another scenario.
+    pub fn reset(&self) {
+        self.known.borrow_mut().clear();
+        self.trace.clear();
+    }
+
+    // Contribution of each rule which fires, combined with the previous ones.
+    fn add(&self, total: Option<f64>, contribution: f64, name: &str) -> f64 {
+        let previous = match total {
+            Some(previous) => previous,
+            None => return contribution,
+        };
+        let combined = self.algebra.combine(previous, contribution);
+        self.trace.note(format!(
+            "{}: {} combined with {} gives {}.",
+            name,
+            round(previous),
+            round(contribution),
+            round(combined)
+        ));
+        combined
+    }
+
+    // Value of a fact: the one given for an initial fact, else the
+    // combination of the rules concluding it or its negation which fire.
+    pub fn fact(&self, fact: &Fact) -> f64 {
+        let name = self.algebra.name();
+        if let Some(value) = self.known.borrow().get(&fact.name) {
+            return *value;
+        }
+        if fact.determined.get() {
+            let value = self.algebra.initial(fact);
+            self.trace.note(format!(
+                "{} has {} {} as an initial fact.",
+                fact.describe(),
+                name,
+                round(value)
+            ));
+            self.known.borrow_mut().insert(fact.name.clone(), value);
+            return value;
+        }
+        // A fact met again while solving itself is worth zero, unknown as a
+        // factor and false as a degree.
+        if !self.solving.borrow_mut().insert(fact.name.clone()) {
+            return 0.0;
+        }
+        let (mut positive, mut negative) = (None, None);
+        for rule in self.rules.iter() {
+            if !rule.implies_fact(fact) {
+                continue;
+            }
+            let negated = fact.reverse_state.replace(false);
+            let premise = self.eval(&rule.lhs, &mut 0);
+            let strength = rule.certainty.unwrap_or(1.0);
+            let contribution = match self.algebra.fire(premise, strength) {
+                Some(contribution) => contribution,
+                None => {
+                    self.trace.note(format!(
+                        "{}: rule {} does not fire, {} has {} {}.",
+                        fact.name,
+                        rule.provenance(),
+                        side_to_infix(&rule.lhs),
+                        name,
+                        round(premise)
+                    ));
+                    continue;
+                }
+            };
+            self.trace.note(format!(
+                "{}: rule {} fires, {} has {} {}, with the strength {} of the rule it gives {}{} {}.",
+                fact.name,
+                rule.provenance(),
+                side_to_infix(&rule.lhs),
+                name,
+                round(premise),
+                strength,
+                if negated { "!" } else { "" },
+                fact.name,
+                round(contribution)
+            ));
+            let total = if negated {
+                &mut negative
+            } else {
+                &mut positive
+            };
+            *total = Some(self.add(*total, contribution, &fact.name));
+        }
+        self.solving.borrow_mut().remove(&fact.name);
+        let value = self
+            .algebra
+            .conclude(positive.unwrap_or(0.0), negative.unwrap_or(0.0));
+        if positive.is_some() && negative.is_some() {
+            self.trace.note(format!(
+                "{}: {} for it and {} against it give {}.",
+                fact.name,
+                round(positive.unwrap_or(0.0)),
+                round(negative.unwrap_or(0.0)),
+                round(value)
+            ));
+        }
+        self.trace.note(format!(
+            "{} has {} {}.",
+            fact.describe(),
+            name,
+            round(value)
+        ));
+        self.known.borrow_mut().insert(fact.name.clone(), value);
+        value
+    }
+
+    // Solved copies of the queries, with their value.
+    pub fn solve(&self, queries: Vec<&Fact>) -> Vec<Fact> {
+        queries
+            .iter()
+            .map(|fact| {
+                let value = self.fact(fact);
+                let solved = fact.copy();
+                let state = self.algebra.state(value, self.options);
+                solved.state.set(state == State::True);
+                solved.determined.set(state != State::Undetermined);
+                solved.measure.set(Some(self.algebra.measure(value)));
+                solved
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,53 @@
+use lib::certainty;
+use lib::expert_system;
+use lib::options::Options;
+use lib::print;
+use lib::regression;
+
+use std::io::Error;
+
+fn with_certainty() -> Result<Options, Error> {
+    let mut options = Options::new();
+    options.load("--certainty")?;
+    Ok(options)
+}
+
+#[test]
+fn certainty_combination() {
+    assert!((certainty::combine(0.6, 0.5) - 0.8).abs() < 1e-9);
+    assert!((certainty::combine(-0.6, -0.5) + 0.8).abs() < 1e-9);
+    assert!((certainty::combine(0.6, -0.3) - 3.0 / 7.0).abs() < 1e-9);
+    assert_eq!(certainty::combine(1.0, -1.0), 0.0);
+}
+#[test]
+fn certainty_expectations() -> Result<(), Error> {
+    assert!(regression::run("testfiles/certainty", &with_certainty()?)?);
+    Ok(())
+}
+#[test]
+fn certainty_is_optional() -> Result<(), Error> {
+    // Without --certainty, factors are read but results stay boolean.
+    let results = expert_system::solve_file("testfiles/certainty/mycin", &Options::new())?;
+    let output = print::scenarios_to_string(&results);
+    assert!(!output.contains("CF"), "{}", output);
+    assert!(output.contains("flu = True"), "{}", output);
+    Ok(())
+}
+#[test]
+fn certainty_explanation() -> Result<(), Error> {
+    let mut options = with_certainty()?;
+    options.load("-e")?;
+    let results = expert_system::solve_file("testfiles/certainty/mycin", &options)?;
+    let explanation = &results[0].explanation;
+    assert!(
+        explanation.contains("cold: 0.36 combined with 0.30 gives 0.55."),
+        "{}",
+        explanation
+    );
+    assert!(
+        explanation.contains("fever has CF 0.90 as an initial fact."),
+        "{}",
+        explanation
+    );
+    Ok(())
+}
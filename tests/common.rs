@@ -16,7 +16,7 @@ fn get_operand(c: char) -> Operand {
     }
 }
 
-fn tokenise_str<'rule>(arg: &str, facts: &'rule Vec<Fact>) -> Vec<Token<'rule>> {
+fn tokenise_str<'rule>(arg: &str, facts: &'rule [Fact]) -> Vec<Token<'rule>> {
     let mut ret: Vec<Token> = Vec::new();
 
     for c in arg.chars() {
@@ -36,7 +36,7 @@ fn cmp_tokens(exp: Vec<Token>, res: Vec<Token>) -> bool {
     }
     while i > 0 {
         i -= 1;
-        if exp[i].cmp_tok(&res[i]) == false {
+        if !exp[i].cmp_tok(&res[i]) {
             return false;
         }
     }
@@ -45,35 +45,35 @@ fn cmp_tokens(exp: Vec<Token>, res: Vec<Token>) -> bool {
 
 pub fn run_test(expr: &str, rslt: &str, ass: bool) -> Result<(), Error> {
     let facts = vec![
-        Fact::new('A'),
-        Fact::new('B'),
-        Fact::new('C'),
-        Fact::new('D'),
-        Fact::new('E'),
-        Fact::new('F'),
-        Fact::new('G'),
-        Fact::new('H'),
-        Fact::new('I'),
-        Fact::new('J'),
-        Fact::new('K'),
-        Fact::new('L'),
-        Fact::new('M'),
-        Fact::new('N'),
-        Fact::new('O'),
-        Fact::new('P'),
-        Fact::new('Q'),
-        Fact::new('R'),
-        Fact::new('S'),
-        Fact::new('T'),
-        Fact::new('U'),
-        Fact::new('V'),
-        Fact::new('W'),
-        Fact::new('X'),
-        Fact::new('Y'),
-        Fact::new('Z'),
+        Fact::new("A"),
+        Fact::new("B"),
+        Fact::new("C"),
+        Fact::new("D"),
+        Fact::new("E"),
+        Fact::new("F"),
+        Fact::new("G"),
+        Fact::new("H"),
+        Fact::new("I"),
+        Fact::new("J"),
+        Fact::new("K"),
+        Fact::new("L"),
+        Fact::new("M"),
+        Fact::new("N"),
+        Fact::new("O"),
+        Fact::new("P"),
+        Fact::new("Q"),
+        Fact::new("R"),
+        Fact::new("S"),
+        Fact::new("T"),
+        Fact::new("U"),
+        Fact::new("V"),
+        Fact::new("W"),
+        Fact::new("X"),
+        Fact::new("Y"),
+        Fact::new("Z"),
     ];
-    let exptok: Vec<Token> = tokenise_str(&expr, &facts);
-    let rsltok: Vec<Token> = tokenise_str(&rslt, &facts);
+    let exptok: Vec<Token> = tokenise_str(expr, &facts);
+    let rsltok: Vec<Token> = tokenise_str(rslt, &facts);
 
     let exptok_rpn = apply_on_vec(&exptok);
     let mut exptok: Vec<Token> = Vec::new();
@@ -81,7 +81,7 @@ pub fn run_test(expr: &str, rslt: &str, ass: bool) -> Result<(), Error> {
     match exptok_rpn {
         Ok(vec) => exptok = vec,
         Err(er) => {
-            if ass == true {
+            if ass {
                 return Err(er);
             }
         }
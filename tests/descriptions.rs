@@ -0,0 +1,31 @@
+use lib::expert_system;
+use lib::lexer;
+use lib::options::Options;
+use lib::print;
+
+use std::io::Error;
+
+#[test]
+fn declaration_syntax() {
+    assert_eq!(
+        lexer::declaration("A: the patient has a fever # comment"),
+        Some(("A", "the patient has a fever"))
+    );
+    assert_eq!(
+        lexer::declaration("  net.link_down : the link is down"),
+        Some(("net.link_down", "the link is down"))
+    );
+    assert_eq!(lexer::declaration("A + B => C"), None);
+    assert_eq!(lexer::declaration("=A"), None);
+}
+#[test]
+fn descriptions_in_results() -> Result<(), Error> {
+    let results = expert_system::solve_file("testfiles/complete/descriptions", &Options::new())?;
+    let flu = &results[0].solved_queries[1];
+    assert_eq!(flu.description.as_deref(), Some("the patient has the flu"));
+    assert_eq!(
+        print::results_to_string(&results[0].solved_queries),
+        "B = True\nflu (the patient has the flu) = True\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use lib::dot;
+use lib::expert_system;
+use lib::facts::Facts;
+use lib::graph::Graph;
+use lib::options::Options;
+use lib::rules::{rule::token::Token, Rules};
+use lib::tree_builder;
+
+use std::fs;
+use std::io::Error;
+
+#[test]
+fn dot_rules_bipartite() -> Result<(), Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_rule(&facts, "A + !B => C", &Options::new())?;
+    rules.as_reverse_polish_notation()?;
+    let dot = dot::rules_to_dot(&rules);
+    assert!(dot.starts_with("digraph rules {"));
+    assert!(dot.contains("rule0 [label=\"r1\\nA + !B => C\", shape=box];"));
+    assert!(dot.contains("fact_A -> rule0;"));
+    assert!(dot.contains("fact_B -> rule0 [style=dashed];"));
+    assert!(dot.contains("rule0 -> fact_C;"));
+    Ok(())
+}
+#[test]
+fn dot_negated_subexpression() -> Result<(), Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_rule(&facts, "!(A + !B) | C => !(D + E)", &Options::new())?;
+    rules.as_reverse_polish_notation()?;
+    let dot = dot::rules_to_dot(&rules);
+    assert!(dot.contains("fact_A -> rule0 [style=dashed];"));
+    assert!(dot.contains("fact_B -> rule0;"));
+    assert!(dot.contains("fact_C -> rule0;"));
+    assert!(dot.contains("rule0 -> fact_D [style=dashed];"));
+    assert!(dot.contains("rule0 -> fact_E [style=dashed];"));
+    Ok(())
+}
+#[test]
+fn dot_files_in_dir() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join(format!("expert_system_dot_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let mut options = Options::new();
+    options.load(&format!("--dot={}", dir.display()))?;
+    expert_system::solve_file("testfiles/complete/constants", &options)?;
+    assert!(fs::read_to_string(dir.join("rules.dot"))?.starts_with("digraph rules {"));
+    assert!(fs::read_dir(&dir)?.count() > 1);
+    fs::remove_dir_all(&dir)
+}
+#[test]
+fn dot_proof_colours() -> Result<(), Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_rule(&facts, "A + B => C", &Options::new())?;
+    rules.as_reverse_polish_notation()?;
+    facts.set_initial_facts("=A", &Options::new())?;
+    let mut graph: Graph<Token> = Graph::new();
+    let root = graph.add_query(Token::new_fact(facts.get("C").unwrap()));
+    let graph = tree_builder::generate(
+        graph,
+        &rules,
+        facts.get("C").unwrap(),
+        root,
+        &Options::new(),
+    )?;
+    let dot = dot::tree_to_dot(&graph, &rules);
+    assert!(dot.contains("node0 [label=\"C\", shape=ellipse, style=filled, fillcolor=lightcoral];"));
+    assert!(dot.contains("[label=\"A\", shape=ellipse, style=filled, fillcolor=palegreen];"));
+    assert!(dot.contains("node0 -> node1;"));
+    Ok(())
+}
+#[test]
+fn dot_proof_rule_labels() -> Result<(), Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_location(Some(String::from("kb:3")));
+    rules.set_rule(&facts, "[fever] A => C", &Options::new())?;
+    rules.as_reverse_polish_notation()?;
+    facts.set_initial_facts("=A", &Options::new())?;
+    let mut graph: Graph<Token> = Graph::new();
+    let root = graph.add_query(Token::new_fact(facts.get("C").unwrap()));
+    let graph = tree_builder::generate(
+        graph,
+        &rules,
+        facts.get("C").unwrap(),
+        root,
+        &Options::new(),
+    )?;
+    assert!(dot::tree_to_dot(&graph, &rules).contains("node0 -> node1 [label=\"fever (kb:3)\"];"));
+    Ok(())
+}
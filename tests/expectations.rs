@@ -0,0 +1,61 @@
+use lib::facts::{Facts, State};
+use lib::options::Options;
+use lib::regression::{self, Outcome};
+
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+
+#[test]
+fn expectations_parse() -> Result<(), Error> {
+    let facts = Facts::new();
+    facts.set_expectations("?= AB true C false # comment", &Options::new())?;
+    assert_eq!(facts.get("A").unwrap().expected.get(), Some(State::True));
+    assert_eq!(facts.get("B").unwrap().expected.get(), Some(State::True));
+    assert_eq!(facts.get("C").unwrap().expected.get(), Some(State::False));
+    assert!(facts.get("C").unwrap().queried.get());
+    assert_eq!(facts.get("D").unwrap().expected.get(), None);
+    Ok(())
+}
+#[test]
+fn expectations_errors() {
+    let options = Options::new();
+    assert!(Facts::new().set_expectations("?= true", &options).is_err());
+    assert!(Facts::new().set_expectations("?= A", &options).is_err());
+    assert!(Facts::new()
+        .set_expectations("?= a true", &options)
+        .is_err());
+    assert!(Facts::new()
+        .set_expectations("?= AA true", &options)
+        .is_err());
+    assert!(Facts::new()
+        .set_expectations("?= A true A false", &options)
+        .is_err());
+}
+#[test]
+fn expectations_check_pass() -> Result<(), Error> {
+    assert!(regression::check(
+        "testfiles/expect/sujet",
+        &Options::new()
+    )?);
+    Ok(())
+}
+#[test]
+fn expectations_check_fail() -> Result<(), Error> {
+    let name = format!("expert_system_expectations_{}", std::process::id());
+    let dir = std::env::temp_dir().join(name);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("case"), "A => B\n=A\n?= B false\n")?;
+    match regression::check_case(&dir.join("case"), &Options::new())? {
+        Outcome::Fail(diff) => assert!(diff.ends_with("-B = False\n+B = True\n")),
+        _ => panic!("unmet expectation should fail"),
+    }
+    fs::remove_dir_all(&dir)
+}
+#[test]
+fn expectations_skip() -> Result<(), Error> {
+    match regression::check_case(Path::new("testfiles/complete/sujet"), &Options::new())? {
+        Outcome::Skip => Ok(()),
+        _ => panic!("file without expectation should be skipped"),
+    }
+}
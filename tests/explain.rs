@@ -0,0 +1,56 @@
+use lib::expert_system;
+use lib::explain::Template;
+use lib::options::Options;
+use lib::regression;
+
+use std::fs;
+use std::io::Error;
+
+fn explain(level: &str) -> Result<Options, Error> {
+    let mut options = Options::new();
+    options.load(level)?;
+    Ok(options)
+}
+
+#[test]
+fn explain_levels() -> Result<(), Error> {
+    let results = expert_system::solve_file("testfiles/complete/sujet", &explain("-e")?)?;
+    assert_eq!(
+        results[0].explanation.lines().nth(1),
+        Some("V is true because C and D are true (rule r8 (sujet:12): C | D => X | V).")
+    );
+    assert_eq!(results[0].explanation.lines().count(), 3);
+    let results = expert_system::solve_file("testfiles/complete/sujet", &explain("-eee")?)?;
+    assert!(results[0]
+        .explanation
+        .contains("\n    A is true because it is an initial fact.\n"));
+    let results = expert_system::solve_file("testfiles/complete/sujet", &Options::new())?;
+    assert!(results[0].explanation.is_empty());
+    Ok(())
+}
+#[test]
+fn explain_regression() -> Result<(), Error> {
+    assert!(regression::run("testfiles/explain", &explain("-ee")?)?);
+    Ok(())
+}
+#[test]
+fn explain_template() -> Result<(), Error> {
+    let mut options = explain("-e")?;
+    options.load("--template=testfiles/templates/french")?;
+    let results = expert_system::solve_file("testfiles/complete/sujet", &options)?;
+    assert_eq!(
+        results[0].explanation.lines().next(),
+        Some("G est vrai car c'est un fait initial.")
+    );
+    Ok(())
+}
+#[test]
+fn explain_template_errors() -> Result<(), Error> {
+    let name = format!("expert_system_template_{}", std::process::id());
+    let path = std::env::temp_dir().join(name);
+    fs::write(&path, "colour = blue\n")?;
+    assert!(Template::load(&path.to_string_lossy()).is_err());
+    fs::write(&path, "no value\n")?;
+    assert!(Template::load(&path.to_string_lossy()).is_err());
+    fs::remove_file(&path)
+}
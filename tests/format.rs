@@ -0,0 +1,64 @@
+use lib::format;
+use lib::options::Options;
+
+use std::fs;
+use std::io::Error;
+
+#[test]
+fn format_canonical() -> Result<(), Error> {
+    let text = "# header\n\n(A+B)|C=>D   # comment\n[r_x]  A^(B^C)  <=>  !(E)\n\
+                A + (B => C) => D\n=A  B\n?(A|B)+!C\n?=  D true\n@  next\n";
+    assert_eq!(
+        format::format_text(text, "input")?,
+        "# header\n\nA + B | C => D # comment\n[r_x] A ^ (B ^ C) <=> !E\n\
+         A + (B => C) => D\n=A B\n?(A | B) + !C\n?= D true\n@ next\n"
+    );
+    Ok(())
+}
+#[test]
+fn format_multiline() -> Result<(), Error> {
+    let text = "atleast(1, # first\n  A,\n  B) => C\n";
+    assert_eq!(
+        format::format_text(text, "input")?,
+        "# first\natleast(1, A, B) => C\n"
+    );
+    Ok(())
+}
+#[test]
+fn format_stable() -> Result<(), Error> {
+    for entry in fs::read_dir("testfiles/complete")? {
+        let path = entry?.path();
+        let name = path.to_string_lossy().to_string();
+        if name.ends_with(".refout") || name.ends_with(".referr") || name.contains("invalide_char")
+        {
+            continue;
+        }
+        let formatted = format::format_text(&fs::read_to_string(&path)?, &name)?;
+        assert_eq!(
+            format::format_text(&formatted, &name)?,
+            formatted,
+            "{}",
+            name
+        );
+    }
+    Ok(())
+}
+#[test]
+fn format_check() -> Result<(), Error> {
+    let path = std::env::temp_dir().join(format!("expert_system_format_{}", std::process::id()));
+    let name = path.to_string_lossy().to_string();
+    fs::write(&path, "A+B=>C\n=A\n?C\n")?;
+    let mut options = Options::new();
+    options.load("--check")?;
+    assert!(!format::run(&name, &options)?);
+    assert_eq!(fs::read_to_string(&path)?, "A+B=>C\n=A\n?C\n");
+    assert!(format::run(&name, &Options::new())?);
+    assert_eq!(fs::read_to_string(&path)?, "A + B => C\n=A\n?C\n");
+    assert!(format::run(&name, &options)?);
+    fs::remove_file(&path)?;
+    Ok(())
+}
+#[test]
+fn format_error() {
+    assert!(format::format_text("A + $ => B\n", "input").is_err());
+}
@@ -0,0 +1,67 @@
+use lib::expert_system;
+use lib::fuzzy::Norm;
+use lib::options::Options;
+use lib::print;
+use lib::regression;
+use lib::rules::rule::token::Operand;
+
+use std::io::Error;
+
+fn fuzzy(norm: &str) -> Result<Options, Error> {
+    let mut options = Options::new();
+    options.load(&format!("--fuzzy={}", norm))?;
+    Ok(options)
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn fuzzy_norms() -> Result<(), Error> {
+    let (a, b) = (0.7, 0.4);
+    let expected = [
+        ("min", 0.4, 0.7, 0.6),
+        ("product", 0.28, 0.82, 0.4896),
+        ("lukasiewicz", 0.1, 1.0, 0.3),
+    ];
+    for (name, and, or, xor) in expected.iter() {
+        let norm = Norm::from_name(name)?;
+        assert!(close(norm.compute(Operand::And, a, b), *and), "{}", name);
+        assert!(close(norm.compute(Operand::Or, a, b), *or), "{}", name);
+        assert!(close(norm.compute(Operand::Not, a, 0.0), 0.3), "{}", name);
+        assert!(close(norm.compute(Operand::Xor, a, b), *xor), "{}", name);
+        assert!(
+            close(norm.compute(Operand::Xnor, a, b), 1.0 - xor),
+            "{}",
+            name
+        );
+    }
+    assert!(Norm::from_name("max").is_err());
+    Ok(())
+}
+#[test]
+fn fuzzy_expectations() -> Result<(), Error> {
+    assert!(regression::run("testfiles/fuzzy", &fuzzy("min")?)?);
+    Ok(())
+}
+#[test]
+fn fuzzy_norm_changes_degrees() -> Result<(), Error> {
+    let results = expert_system::solve_file("testfiles/fuzzy/risk", &fuzzy("lukasiewicz")?)?;
+    assert_eq!(
+        print::scenarios_to_string(&results),
+        "review = False (degree 0.50)\n\
+         risk = True (degree 0.90)\n\
+         risk + savings = False (degree 0.30)\n"
+    );
+    Ok(())
+}
+#[test]
+fn fuzzy_options() -> Result<(), Error> {
+    assert!(fuzzy("min")?.load("--certainty").is_err());
+    assert!(fuzzy("max").is_err());
+    let mut options = Options::new();
+    options.load("--certainty")?;
+    assert!(options.load("--fuzzy").is_err());
+    Ok(())
+}
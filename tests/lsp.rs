@@ -0,0 +1,273 @@
+use lib::json::Value;
+use lib::lsp::{self, analysis};
+
+use std::fs;
+use std::io::{Cursor, Error};
+use std::path::{Path, PathBuf};
+
+fn frame(messages: &[String]) -> Cursor<Vec<u8>> {
+    let mut input = String::new();
+    for message in messages {
+        input.push_str(&format!(
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        ));
+    }
+    Cursor::new(input.into_bytes())
+}
+
+fn replies(output: Vec<u8>) -> Vec<Value> {
+    let output = String::from_utf8(output).unwrap();
+    output
+        .split("Content-Length: ")
+        .filter(|part| !part.is_empty())
+        .map(|part| Value::parse(part.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect()
+}
+
+fn serve(messages: &[String]) -> Result<Vec<Value>, Error> {
+    let mut output = Vec::new();
+    lsp::run(frame(messages), &mut output)?;
+    Ok(replies(output))
+}
+
+fn open(path: &Path, text: &str) -> String {
+    let uri = Value::String(analysis::path_to_uri(path));
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":{},"text":{}}}}}}}"#,
+        uri,
+        Value::string(text)
+    )
+}
+
+fn at(id: usize, method: &str, path: &Path, line: usize, character: usize, extra: &str) -> String {
+    let uri = Value::String(analysis::path_to_uri(path));
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{{"textDocument":{{"uri":{}}},"position":{{"line":{},"character":{}}}{}}}}}"#,
+        id, method, uri, line, character, extra
+    )
+}
+
+fn reply(replies: &[Value], id: usize) -> &Value {
+    replies
+        .iter()
+        .find(|reply| reply.get("id").as_usize() == Some(id))
+        .unwrap()
+}
+
+fn workspace(name: &str, files: &[(&str, &str)]) -> Result<PathBuf, Error> {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    for (file, text) in files {
+        fs::write(dir.join(file), text)?;
+    }
+    dir.canonicalize()
+}
+
+#[test]
+fn json_round_trip() -> Result<(), Error> {
+    let text = r#"{"a":[1,2.5,true,null],"b":"x\"\né","c":{}}"#;
+    let value = Value::parse(text)?;
+    assert_eq!(value.get("a").as_array().unwrap().len(), 4);
+    assert_eq!(value.get("b").as_str(), Some("x\"\né"));
+    assert_eq!(Value::parse(&value.to_string())?, value);
+    assert!(Value::parse("[1,").is_err());
+    Ok(())
+}
+
+#[test]
+fn json_surrogates() -> Result<(), Error> {
+    assert_eq!(
+        Value::parse(r#""\ud83d\ude00""#)?.as_str(),
+        Some("\u{1F600}")
+    );
+    for text in [r#""\ud800\u0041""#, r#""\ud800""#, r#""\udc00""#] {
+        let error = Value::parse(text).unwrap_err();
+        assert!(error.to_string().starts_with("Json: "), "{}", error);
+    }
+    Ok(())
+}
+
+#[test]
+fn json_depth() -> Result<(), Error> {
+    let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+    assert!(Value::parse(&nested(64)).is_ok());
+    let error = Value::parse(&nested(65)).unwrap_err();
+    assert!(error.to_string().starts_with("Json: "), "{}", error);
+    assert!(Value::parse(&"[".repeat(200_000)).is_err());
+    assert!(Value::parse(&r#"{"a":"#.repeat(200_000)).is_err());
+    Ok(())
+}
+
+#[test]
+fn lsp_message_too_long() {
+    let input = format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX);
+    let error = lsp::run(Cursor::new(input.into_bytes()), Vec::new()).unwrap_err();
+    assert!(error.to_string().starts_with("Lsp: "), "{}", error);
+}
+
+#[test]
+fn lsp_initialize_and_shutdown() -> Result<(), Error> {
+    let replies = serve(&[
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#.to_string(),
+        r#"{"jsonrpc":"2.0","id":2,"method":"unknown/method"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ])?;
+    assert_eq!(replies.len(), 3);
+    let capabilities = reply(&replies, 1).get("result").get("capabilities");
+    assert_eq!(capabilities.get("hoverProvider").as_bool(), Some(true));
+    assert_eq!(capabilities.get("renameProvider").as_bool(), Some(true));
+    assert_eq!(
+        reply(&replies, 2).get("error").get("code").as_f64(),
+        Some(-32601.0)
+    );
+    assert!(reply(&replies, 3).get("result").is_null());
+    Ok(())
+}
+
+#[test]
+fn lsp_diagnostics() -> Result<(), Error> {
+    let dir = workspace("expert_system_lsp_diagnostics", &[("main", "")])?;
+    let main = dir.join("main");
+    let replies = serve(&[
+        open(&main, "A + => B\n=A\n?B\n"),
+        open(&main, "A => B\nA => 1\n=A\n?B\n"),
+        open(&main, "A => B\n=A\n?B\n"),
+    ])?;
+    let diagnostics: Vec<&Vec<Value>> = replies
+        .iter()
+        .map(|reply| reply.get("params").get("diagnostics").as_array().unwrap())
+        .collect();
+    assert_eq!(diagnostics.len(), 3);
+    assert_eq!(
+        diagnostics[0][0].get("message").as_str(),
+        Some("Syntax: missing operand")
+    );
+    assert_eq!(
+        diagnostics[0][0]
+            .get("range")
+            .get("start")
+            .get("character")
+            .as_usize(),
+        Some(4)
+    );
+    let message = diagnostics[1][0].get("message").as_str().unwrap();
+    assert_eq!(message, "main:2: Rules: constant in conclusion (at A => 1)");
+    assert_eq!(
+        diagnostics[1][0]
+            .get("range")
+            .get("start")
+            .get("line")
+            .as_usize(),
+        Some(1)
+    );
+    assert!(diagnostics[2].is_empty());
+    Ok(())
+}
+
+#[test]
+fn lsp_navigation() -> Result<(), Error> {
+    let dir = workspace(
+        "expert_system_lsp_navigation",
+        &[
+            (
+                "common",
+                "fever: body temperature above 38\nA | B => fever\n",
+            ),
+            (
+                "main",
+                "include \"common\"\nfever + C => alert\n\n=AC\n?alert\n",
+            ),
+        ],
+    )?;
+    let main = dir.join("main");
+    let common = dir.join("common");
+    let text = fs::read_to_string(&main)?;
+    let replies = serve(&[
+        open(&main, &text),
+        at(1, "textDocument/hover", &main, 1, 2, ""),
+        at(2, "textDocument/definition", &main, 1, 2, ""),
+        at(3, "textDocument/references", &main, 1, 2, ""),
+        at(4, "textDocument/hover", &main, 2, 0, ""),
+    ])?;
+    let hover = reply(&replies, 1)
+        .get("result")
+        .get("contents")
+        .get("value");
+    let hover = hover.as_str().unwrap();
+    assert!(hover.contains("body temperature above 38"), "{}", hover);
+    assert!(hover.contains("A | B => fever"), "{}", hover);
+
+    let definition = reply(&replies, 2).get("result").as_array().unwrap();
+    assert_eq!(definition.len(), 1);
+    assert_eq!(
+        definition[0].get("uri").as_str(),
+        Some(analysis::path_to_uri(&common).as_str())
+    );
+    let start = definition[0].get("range").get("start");
+    assert_eq!(start.get("line").as_usize(), Some(1));
+    assert_eq!(start.get("character").as_usize(), Some(9));
+
+    let references = reply(&replies, 3).get("result").as_array().unwrap();
+    assert_eq!(references.len(), 3);
+    assert!(reply(&replies, 4).get("result").is_null());
+    Ok(())
+}
+
+#[test]
+fn lsp_rename() -> Result<(), Error> {
+    let dir = workspace(
+        "expert_system_lsp_rename",
+        &[
+            ("common", "A | B => C\n"),
+            ("main", "include \"common\"\nC + D => E\n=ABD\n?CE\n"),
+        ],
+    )?;
+    let main = dir.join("main");
+    let text = fs::read_to_string(&main)?;
+    let replies = serve(&[
+        open(&main, &text),
+        at(
+            1,
+            "textDocument/rename",
+            &main,
+            1,
+            0,
+            r#","newName":"cold""#,
+        ),
+        at(
+            2,
+            "textDocument/rename",
+            &main,
+            1,
+            0,
+            r#","newName":"bad name""#,
+        ),
+    ])?;
+    let changes = reply(&replies, 1).get("result").get("changes");
+    let in_main = changes
+        .get(&analysis::path_to_uri(&main))
+        .as_array()
+        .unwrap();
+    let in_common = changes
+        .get(&analysis::path_to_uri(&dir.join("common")))
+        .as_array()
+        .unwrap();
+    assert_eq!(in_common.len(), 1);
+    assert_eq!(in_common[0].get("newText").as_str(), Some("cold"));
+    let texts: Vec<&str> = in_main
+        .iter()
+        .map(|edit| edit.get("newText").as_str().unwrap())
+        .collect();
+    // The C of "?CE" is the start of a run.
+    assert_eq!(texts, vec!["cold", "cold "]);
+    assert_eq!(
+        reply(&replies, 2).get("error").get("code").as_f64(),
+        Some(-32602.0)
+    );
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use lib::facts::Facts;
+use lib::options::Options;
+use lib::regression;
+use lib::source::Source;
+
+use std::io::Error;
+use std::path::Path;
+use std::rc::Rc;
+
+#[test]
+fn modules_source() -> Result<(), Error> {
+    let source = Source::load(Path::new("testfiles/modules/main"))?;
+    let network = source.modules.get("network").unwrap().as_ref().unwrap();
+    assert!(network.contains("link_down") && network.contains("failure"));
+    assert!(!source
+        .modules
+        .get("power")
+        .unwrap()
+        .as_ref()
+        .unwrap()
+        .contains("failure"));
+    let facts = Facts::from_source(&source);
+    assert!(facts.get("network.failure").is_some());
+    assert!(facts.get("power.failure").is_some());
+    assert!(facts.get("failure").is_none());
+    Ok(())
+}
+#[test]
+fn modules_lookup() -> Result<(), Error> {
+    let source = Source::load(Path::new("testfiles/modules/main"))?;
+    let facts = Facts::from_source(&source);
+    assert_eq!(facts.lookup("network.link_down")?.name, "network.link_down");
+    assert!(facts.lookup("power.failure").is_err());
+    assert!(facts.lookup("failure").is_err());
+    facts.set_scope(Some(Rc::new(String::from("power"))));
+    assert_eq!(facts.lookup("failure")?.name, "power.failure");
+    assert_eq!(facts.lookup("network.failure")?.name, "network.failure");
+    Ok(())
+}
+#[test]
+fn modules_names() -> Result<(), Error> {
+    let facts = Facts::new();
+    assert_eq!(facts.lookup("A")?.name, "A");
+    assert!(facts.lookup("fever").is_err());
+    assert!(facts.lookup("a..b").is_err());
+    Ok(())
+}
+#[test]
+fn regression_modules() -> Result<(), Error> {
+    assert!(regression::run("testfiles/modules", &Options::new())?);
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use lib::facts::{Facts, State};
+use lib::json::Value;
+use lib::lexer;
+use lib::options::Options;
+use lib::regression;
+use lib::rules::Rules;
+use lib::serve::Server;
+use lib::solver;
+use lib::source::Source;
+
+use std::io::Error;
+use std::path::Path;
+
+#[test]
+fn numeric_expectations() -> Result<(), Error> {
+    assert!(regression::run("testfiles/numeric", &Options::new())?);
+    Ok(())
+}
+#[test]
+fn numeric_comparisons_read() {
+    assert_eq!(
+        lexer::comparisons("t >= 38 + !(age<2) => fever # age > 3"),
+        vec![("t".to_string(), ">=", 38.0), ("age".to_string(), "<", 2.0)]
+    );
+    assert_eq!(lexer::comparison(" != -1.5 => A"), Some(("!=", -1.5, 8)));
+    assert_eq!(lexer::comparison(" <=> B"), None);
+    assert_eq!(lexer::comparison(" => B"), None);
+    assert_eq!(lexer::value(" = 39.2 cough"), Some(("39.2", 7)));
+}
+#[test]
+fn numeric_values_through_the_api() -> Result<(), Error> {
+    let source = Source::load(Path::new("testfiles/numeric/fever"))?;
+    let facts = Facts::from_source(&source);
+    let options = Options::new();
+    let mut rules = Rules::new();
+    rules.set_rule(&facts, "temperature > 38 => fever", &options)?;
+    rules.as_reverse_polish_notation()?;
+    let fever = facts.get("fever").unwrap();
+    facts.set_value("temperature", 37.0)?;
+    let solved = solver::solve(vec![fever], &rules, &options)?;
+    assert_eq!(solved[0].solved_state(), State::False);
+
+    facts.reset();
+    facts.set_value("temperature", 38.5)?;
+    let solved = solver::solve(vec![fever], &rules, &options)?;
+    assert_eq!(solved[0].solved_state(), State::True);
+    assert!(facts.set_value("temperature > 38", 1.0).is_err());
+    assert!(facts.set_value("unknown", 1.0).is_err());
+    Ok(())
+}
+#[test]
+fn numeric_values_served() -> Result<(), Error> {
+    let mut server = Server::load(&["testfiles/numeric/fever"], Options::new())?;
+    let (status, body) = server.handle(
+        "POST",
+        "/kb/fever/query",
+        r#"{"facts": ["temperature = 37.5"], "queries": ["fever"]}"#,
+    );
+    assert_eq!(status, 200);
+    let results = body.get("results").as_array().unwrap();
+    assert_eq!(results[0].get("state").as_str(), Some("False"));
+
+    let (_, body) = server.handle("GET", "/kb/fever/facts", "");
+    let facts = body.get("facts").as_array().unwrap();
+    let temperature = facts
+        .iter()
+        .find(|fact| fact.get("name").as_str() == Some("temperature"))
+        .unwrap();
+    assert_eq!(temperature.get("value"), &Value::Number(39.2));
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use lib::facts::{Facts, State};
+use lib::options::Options;
+use lib::regression;
+
+use std::io::Error;
+
+fn open_world() -> Result<Options, Error> {
+    let mut options = Options::new();
+    options.load("-o")?;
+    Ok(options)
+}
+
+#[test]
+fn negative_initial_facts() -> Result<(), Error> {
+    let facts = Facts::new();
+    facts.set_initial_facts("=A !B C", &Options::new())?;
+    assert_eq!(facts.get("A").unwrap().solved_state(), State::True);
+    assert_eq!(facts.get("B").unwrap().solved_state(), State::False);
+    assert_eq!(facts.get("C").unwrap().solved_state(), State::True);
+    assert_eq!(facts.get("D").unwrap().solved_state(), State::Undetermined);
+    Ok(())
+}
+#[test]
+fn negative_initial_facts_errors() {
+    let options = Options::new();
+    assert!(Facts::new().set_initial_facts("=!A A", &options).is_err());
+    assert!(Facts::new().set_initial_facts("=!!A", &options).is_err());
+    assert!(Facts::new().set_initial_facts("=! A", &options).is_err());
+    assert!(Facts::new().set_initial_facts("=A!", &options).is_err());
+}
+#[test]
+fn open_world_expectations() -> Result<(), Error> {
+    assert!(regression::run("testfiles/open_world", &open_world()?)?);
+    Ok(())
+}
+#[test]
+fn closed_world_expectations() -> Result<(), Error> {
+    assert!(!regression::run("testfiles/open_world", &Options::new())?);
+    Ok(())
+}
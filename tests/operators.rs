@@ -0,0 +1,80 @@
+use lib::facts::Facts;
+use lib::options::Options;
+use lib::rules::Rules;
+
+use std::io::Error;
+
+fn infix(rule: &str) -> Result<String, Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_rule(&facts, rule, &Options::new())?;
+    rules.as_reverse_polish_notation()?;
+    Ok(rules.iter().last().unwrap().to_infix())
+}
+
+#[test]
+fn operators_priority() -> Result<(), Error> {
+    assert_eq!(infix("A + B !| C => D")?, "(A + B) !| C => D");
+    assert_eq!(infix("A !^ B | C !+ D => E")?, "A !^ (B | (C !+ D)) => E");
+    assert_eq!(
+        infix("(A | B <=> C + D) => E")?,
+        "((A | B) <=> (C + D)) => E"
+    );
+    assert_eq!(infix("(A => B => C) => D")?, "(A => (B => C)) => D");
+    assert_eq!(infix("(A => B) => C")?, "(A => B) => C");
+    Ok(())
+}
+#[test]
+fn operators_conclusion() -> Result<(), Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_rule(&facts, "A => !(B | C) + D", &Options::new())?;
+    rules.as_reverse_polish_notation()?;
+    let rule = rules.iter().next().unwrap();
+    for (name, is_not) in [("B", true), ("C", true), ("D", false)] {
+        let fact = facts.get(name).unwrap();
+        assert!(rule.implies_fact(fact));
+        assert_eq!(fact.reverse_state.get(), is_not);
+    }
+    assert!(!rule.implies_fact(facts.get("A").unwrap()));
+    for conclusion in ["B !^ C", "(B => C)", "B !+ C", "B !| C"] {
+        assert!(rules
+            .set_rule(&facts, &format!("A => {}", conclusion), &Options::new())
+            .is_err());
+    }
+    Ok(())
+}
+#[test]
+fn operators_cardinality() -> Result<(), Error> {
+    assert_eq!(
+        infix("atleast(2, A, B + C, !D) => E")?,
+        "atleast(2, A, B + C, !D) => E"
+    );
+    assert_eq!(
+        infix("exactly( 1 , A | B, C) + atmost(0, D) => E")?,
+        "exactly(1, A | B, C) + atmost(0, D) => E"
+    );
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    assert!(rules
+        .set_rule(&facts, "atleast(256, A) => B", &Options::new())
+        .is_err());
+    assert!(rules
+        .set_rule(&facts, "atleast(1 A) => B", &Options::new())
+        .is_err());
+    Ok(())
+}
+#[test]
+fn operators_constants() -> Result<(), Error> {
+    assert_eq!(infix("TRUE + A => B")?, "TRUE + A => B");
+    assert_eq!(infix("!0 | 1 => B")?, "!FALSE | TRUE => B");
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    assert!(rules
+        .set_rule(&facts, "A => FALSE", &Options::new())
+        .is_err());
+    assert!(rules
+        .set_rule(&facts, "TRUE FALSE => A", &Options::new())
+        .is_err());
+    Ok(())
+}
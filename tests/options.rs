@@ -0,0 +1,44 @@
+use lib::options::Options;
+
+use std::io::Error;
+
+#[test]
+fn options_short_and_long() -> Result<(), Error> {
+    let mut options = Options::new();
+    options.load("-gee")?;
+    options.load("--port=9000")?;
+    options.load("--samples=500")?;
+    assert!(options.graph);
+    assert_eq!(options.explain, 2);
+    assert_eq!(options.port, Some(9000));
+    assert_eq!(options.samples, Some(500));
+    assert!(options.probability);
+    Ok(())
+}
+
+#[test]
+fn options_rejected() {
+    for option in [
+        "--certainy",
+        "--port=abc",
+        "--port=70000",
+        "--samples=abc",
+        "--samples=0",
+        "--check=yes",
+        "-x",
+    ] {
+        let mut options = Options::new();
+        assert!(options.load(option).is_err(), "{}", option);
+    }
+    let mut options = Options::new();
+    assert_eq!(
+        options.load("--certainy").unwrap_err().to_string(),
+        "Options: unknown option --certainy"
+    );
+    assert!(!options.certainty && !options.comment && options.explain == 0);
+    options.load("--samples=10").unwrap();
+    assert_eq!(
+        options.load("--fuzzy").unwrap_err().to_string(),
+        "Options: --fuzzy conflicts with --probability"
+    );
+}
@@ -0,0 +1,142 @@
+use lib::circuit::{self, Circuit};
+use lib::expert_system;
+use lib::facts::{Measure, State};
+use lib::options::Options;
+use lib::regression;
+
+use std::io::Error;
+
+fn options(option: &str) -> Result<Options, Error> {
+    let mut options = Options::new();
+    options.load(option)?;
+    Ok(options)
+}
+
+#[test]
+fn probability_expectations() -> Result<(), Error> {
+    assert!(regression::run(
+        "testfiles/probability",
+        &options("--probability")?
+    )?);
+    Ok(())
+}
+#[test]
+fn probability_exact_count() -> Result<(), Error> {
+    let results = expert_system::solve_file(
+        "testfiles/probability/diagnosis",
+        &options("--probability")?,
+    )?;
+    let measures: Vec<(String, Option<Measure>)> = results[0]
+        .solved_queries
+        .iter()
+        .map(|fact| (fact.name.clone(), fact.measure.get()))
+        .collect();
+    let expected = [("antihistamine", 0.46), ("flu", 0.4), ("sneeze", 0.58)];
+    for ((name, measure), (expected_name, expected)) in measures.iter().zip(expected.iter()) {
+        assert_eq!(name, expected_name);
+        match measure {
+            Some(Measure::Probability(p)) => assert!((p - expected).abs() < 1e-9, "{}", name),
+            other => panic!("{}: {:?}", name, other),
+        }
+    }
+    let (_, state, measure) = &results[0].expressions[0];
+    assert_eq!(*state, State::False);
+    assert!(matches!(measure, Some(Measure::Probability(p)) if (p - 0.12).abs() < 1e-9));
+    Ok(())
+}
+#[test]
+fn probability_sampling() -> Result<(), Error> {
+    // At least 7 of 14 fair coins, 9908 / 16384.
+    let exact = 9908.0 / 16384.0;
+    let results =
+        expert_system::solve_file("testfiles/probability/many", &options("--probability")?)?;
+    match results[0].solved_queries[0].measure.get() {
+        Some(Measure::Probability(p)) => assert!((p - exact).abs() < 1e-9, "{}", p),
+        other => panic!("{:?}", other),
+    }
+    let results =
+        expert_system::solve_file("testfiles/probability/many", &options("--samples=20000")?)?;
+    match results[0].solved_queries[0].measure.get() {
+        Some(Measure::Estimate(p)) => assert!((p - exact).abs() < 0.02, "{}", p),
+        other => panic!("{:?}", other),
+    }
+    let results = expert_system::solve_file(
+        "testfiles/probability/diagnosis",
+        &options("--samples=20000")?,
+    )?;
+    match results[0].solved_queries[1].measure.get() {
+        Some(Measure::Estimate(p)) => assert!((p - 0.4).abs() < 0.02, "{}", p),
+        other => panic!("{:?}", other),
+    }
+    Ok(())
+}
+#[test]
+fn probability_circuit() {
+    let mut circuit = Circuit::new(vec![0.5, 0.2, 0.4]);
+    let (a, b, c) = (
+        circuit.variable(0),
+        circuit.variable(1),
+        circuit.variable(2),
+    );
+    // a shared by both sides: 0.5 * (1 - 0.8 * 0.6).
+    let (ab, ac) = (circuit.and(a, b), circuit.and(a, c));
+    let either = circuit.or(ab, ac);
+    assert_eq!(
+        circuit.probability(either).map(|p| (p - 0.26).abs() < 1e-9),
+        Some(true)
+    );
+    // 0.5 * 0.2 * 0.6 + 0.5 * 0.8 * 0.4 + 0.5 * 0.2 * 0.4 + 0.5 * 0.2 * 0.4.
+    let two = circuit.at_least(2, &[a, b, c]);
+    assert_eq!(
+        circuit.probability(two).map(|p| (p - 0.3).abs() < 1e-9),
+        Some(true)
+    );
+    let not_a = circuit.not(a);
+    assert_eq!(circuit.and(a, not_a), circuit::FALSE);
+}
+#[test]
+fn probability_explanation() -> Result<(), Error> {
+    let mut options = options("--probability")?;
+    options.load("-e")?;
+    let results = expert_system::solve_file("testfiles/probability/diagnosis", &options)?;
+    let explanation = &results[0].explanation;
+    assert!(
+        explanation.contains("fever has prior probability 0.80."),
+        "{}",
+        explanation
+    );
+    assert!(
+        explanation.contains("Counted exactly over 3 uncertain facts"),
+        "{}",
+        explanation
+    );
+    Ok(())
+}
+#[test]
+fn probability_loop() -> Result<(), Error> {
+    // Where a loop is cut depends on the world, which is solved apart.
+    let mut options = options("--probability")?;
+    options.load("-e")?;
+    let results = expert_system::solve_file("testfiles/probability/loop", &options)?;
+    let explanation = &results[0].explanation;
+    assert!(
+        explanation.contains("The rules loop, each world is solved apart."),
+        "{}",
+        explanation
+    );
+    assert!(
+        explanation.contains("Counted over the 4 worlds of 2 uncertain facts."),
+        "{}",
+        explanation
+    );
+    Ok(())
+}
+#[test]
+fn probability_is_exclusive() -> Result<(), Error> {
+    let mut probability = options("--probability")?;
+    assert!(probability.load("--certainty").is_err());
+    assert!(probability.load("--fuzzy=product").is_err());
+    probability.load("--samples=100")?;
+    assert!(options("--samples=100")?.load("--certainty").is_err());
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use lib::expert_system;
+use lib::facts::{Facts, State};
+use lib::lexer;
+use lib::options::Options;
+use lib::rules::Rules;
+
+use std::io::Error;
+
+#[test]
+fn queries_expression_detection() {
+    assert!(lexer::is_expression("?(A + !B) | C"));
+    assert!(lexer::is_expression("?!A"));
+    assert!(!lexer::is_expression("?ABG"));
+    assert!(!lexer::is_expression(
+        "?network.up fever # (not an expression)"
+    ));
+}
+#[test]
+fn queries_expression_parse() -> Result<(), Error> {
+    let facts = Facts::new();
+    let options = Options::new();
+    let expression = Rules::expression(&facts, "?(A + !B) | C", &options)?;
+    assert_eq!(expression.label, "(A + !B) | C");
+    assert!(Rules::expression(&facts, "?A => B", &options).is_err());
+    assert!(Rules::expression(&facts, "?A B", &options).is_err());
+    assert!(Rules::expression(&facts, "?()", &options).is_err());
+    Ok(())
+}
+#[test]
+fn queries_expression_results() -> Result<(), Error> {
+    let results =
+        expert_system::solve_file("testfiles/complete/expression_queries", &Options::new())?;
+    let values: Vec<(&str, State)> = results[0]
+        .expressions
+        .iter()
+        .map(|(expression, state, _)| (expression.as_str(), *state))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            ("(A + !B) | C", State::True),
+            ("!D + (E ^ D)", State::True),
+            ("atleast(2, A, D, E) + !online", State::True),
+        ]
+    );
+    assert_eq!(results[0].solved_queries.len(), 2);
+    assert_eq!(results[1].expressions[0].1, State::False);
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use lib::diff;
+use lib::options::Options;
+use lib::regression::{self, Outcome};
+
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+
+#[test]
+fn regression_complete() -> Result<(), Error> {
+    assert!(regression::run("testfiles/complete", &Options::new())?);
+    Ok(())
+}
+#[test]
+fn regression_error() -> Result<(), Error> {
+    assert!(regression::run("testfiles/error", &Options::new())?);
+    Ok(())
+}
+#[test]
+fn regression_mismatch() -> Result<(), Error> {
+    let name = format!("expert_system_regression_mismatch_{}", std::process::id());
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("case"), "A => B\n=A\n?B\n")?;
+    fs::write(dir.join("case.refout"), "B = False\n")?;
+    match regression::run_case(&dir.join("case"), &Options::new())? {
+        Outcome::Fail(diff) => assert!(diff.ends_with("@@ -1 +1 @@\n-B = False\n+B = True\n")),
+        _ => panic!("mismatching output should fail"),
+    }
+    assert!(!regression::run(&dir.to_string_lossy(), &Options::new())?);
+    fs::write(dir.join("case.refout"), "B = True\n")?;
+    fs::write(dir.join("case.referr"), "Rules: doublon\n")?;
+    match regression::run_case(&dir.join("case"), &Options::new())? {
+        Outcome::Fail(diff) => assert!(diff.ends_with("@@ -1 +0,0 @@\n-Rules: doublon\n")),
+        _ => panic!("missing error should fail"),
+    }
+    fs::remove_dir_all(&dir)
+}
+#[test]
+fn regression_skip() -> Result<(), Error> {
+    match regression::run_case(Path::new("testfiles/graph/graph00"), &Options::new())? {
+        Outcome::Skip => Ok(()),
+        _ => panic!("input without expected file should be skipped"),
+    }
+}
+#[test]
+fn diff_identical() {
+    assert_eq!(diff::unified("A = True\n", "A = True\n", "a", "b"), "");
+}
+#[test]
+fn diff_hunks() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+    let expected = "--- old\n+++ new\n\
+                    @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+                    @@ -11,3 +11,4 @@\n k\n l\n m\n+n\n";
+    assert_eq!(diff::unified(old, new, "old", "new"), expected);
+}
+#[test]
+fn regression_expect() -> Result<(), Error> {
+    assert!(regression::run("testfiles/expect", &Options::new())?);
+    Ok(())
+}
+#[test]
+fn regression_include() -> Result<(), Error> {
+    assert!(regression::run("testfiles/include", &Options::new())?);
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use lib::facts::Facts;
+use lib::options::Options;
+use lib::rules::Rules;
+
+use std::io::Error;
+
+#[test]
+fn rule_labels() -> Result<(), Error> {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_location(Some(String::from("kb:1")));
+    rules.set_rule(&facts, "A => B", &Options::new())?;
+    rules.set_location(Some(String::from("kb:2")));
+    rules.set_rule(&facts, "[equiv] B <=> C", &Options::new())?;
+    rules.set_rule(&facts, "C => D", &Options::new())?;
+    let provenances: Vec<String> = rules.iter().map(|rule| rule.provenance()).collect();
+    assert_eq!(
+        provenances,
+        vec![
+            "r1 (kb:1)",
+            "equiv.reverse (kb:2)",
+            "equiv (kb:2)",
+            "r3 (kb:2)"
+        ]
+    );
+    assert!(rules
+        .set_rule(&facts, "[r1] D => E", &Options::new())
+        .is_err());
+    assert!(rules
+        .set_rule(&facts, "[] D => E", &Options::new())
+        .is_err());
+    Ok(())
+}
+#[test]
+fn rule_errors_carry_provenance() {
+    let facts = Facts::new();
+    let mut rules = Rules::new();
+    rules.set_location(Some(String::from("kb:7")));
+    rules
+        .set_rule(&facts, "[broken] (A => B", &Options::new())
+        .unwrap();
+    let error = rules.as_reverse_polish_notation().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "kb:7: Rule broken: Rpn: brackets do not match (closing missing)"
+    );
+}
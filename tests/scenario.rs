@@ -0,0 +1,39 @@
+use lib::expert_system;
+use lib::options::Options;
+use lib::scenario::Scenario;
+
+use std::io::Error;
+
+#[test]
+fn scenario_name() -> Result<(), Error> {
+    let scenario = Scenario::from_line("@ fever # high temperature")?;
+    assert_eq!(scenario.name, Some(String::from("fever")));
+    let scenario = Scenario::from_line("  @cold")?;
+    assert_eq!(scenario.name, Some(String::from("cold")));
+    Ok(())
+}
+#[test]
+fn scenario_bad_name() {
+    assert!(Scenario::from_line("@").is_err());
+    assert!(Scenario::from_line("@ # no name").is_err());
+    assert!(Scenario::from_line("@ two words").is_err());
+}
+#[test]
+fn scenario_results() -> Result<(), Error> {
+    let results = expert_system::solve_file("testfiles/complete/scenarios", &Options::new())?;
+    let names: Vec<&str> = results
+        .iter()
+        .map(|result| result.name.as_deref().unwrap_or(""))
+        .collect();
+    assert_eq!(names, vec!["nothing", "only_d", "a_and_b"]);
+    assert!(!results[1].solved_queries[0].state.get());
+    assert!(results[2].solved_queries[0].state.get());
+    Ok(())
+}
+#[test]
+fn scenario_default_only() -> Result<(), Error> {
+    let results = expert_system::solve_file("testfiles/complete/sujet", &Options::new())?;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].name.is_none());
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use lib::json::Value;
+use lib::options::Options;
+use lib::serve::{self, Server};
+
+use std::io::{Cursor, Error};
+
+fn server() -> Result<Server, Error> {
+    Server::load(
+        &[
+            "testfiles/complete/descriptions",
+            "testfiles/complete/constants",
+        ],
+        Options::new(),
+    )
+}
+
+fn states(body: &Value) -> Vec<String> {
+    body.get("results")
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            format!(
+                "{} = {}",
+                result.get("query").as_str().unwrap(),
+                result.get("state").as_str().unwrap()
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn serve_lists() -> Result<(), Error> {
+    let mut server = server()?;
+    let (status, body) = server.handle("GET", "/kb", "");
+    assert_eq!(status, 200);
+    let bases = body.get("knowledge_bases").as_array().unwrap();
+    assert_eq!(bases[0].get("name").as_str(), Some("descriptions"));
+    assert_eq!(bases[1].get("name").as_str(), Some("constants"));
+
+    let (status, body) = server.handle("GET", "/kb/descriptions/facts", "");
+    assert_eq!(status, 200);
+    let facts = body.get("facts").as_array().unwrap();
+    let flu = facts
+        .iter()
+        .find(|fact| fact.get("name").as_str() == Some("flu"))
+        .unwrap();
+    assert_eq!(
+        flu.get("description").as_str(),
+        Some("the patient has the flu")
+    );
+
+    let (status, body) = server.handle("GET", "/kb/descriptions/rules", "");
+    assert_eq!(status, 200);
+    let rules = body.get("rules").as_array().unwrap();
+    assert_eq!(rules[0].get("rule").as_str(), Some("A + cough => flu"));
+    assert_eq!(rules[0].get("location").as_str(), Some("descriptions:6"));
+    Ok(())
+}
+
+#[test]
+fn serve_query() -> Result<(), Error> {
+    let mut server = server()?;
+    let (status, body) = server.handle(
+        "POST",
+        "/kb/descriptions/query",
+        r#"{"queries": ["flu", "B", "flu + !B"]}"#,
+    );
+    assert_eq!(status, 200);
+    assert_eq!(
+        states(&body),
+        vec!["B = True", "flu = True", "flu + !B = False"]
+    );
+    assert!(body.get("explanation").is_null());
+
+    // Facts of the body are used for this query only.
+    let (_, body) = server.handle(
+        "POST",
+        "/kb/descriptions/query",
+        r#"{"facts": ["A"], "queries": ["flu"]}"#,
+    );
+    assert_eq!(states(&body), vec!["flu = False"]);
+    let (_, body) = server.handle("POST", "/kb/descriptions/query", r#"{"queries": ["flu"]}"#);
+    assert_eq!(states(&body), vec!["flu = True"]);
+    Ok(())
+}
+
+#[test]
+fn serve_facts_and_explain() -> Result<(), Error> {
+    let mut server = server()?;
+    let (status, _) = server.handle("POST", "/kb/descriptions/facts", r#"{"facts": ["cough"]}"#);
+    assert_eq!(status, 200);
+    let (status, body) = server.handle("POST", "/kb/descriptions/explain", r#"{"queries": ["B"]}"#);
+    assert_eq!(status, 200);
+    assert_eq!(states(&body), vec!["B = False"]);
+    let explanation = body.get("explanation").as_str().unwrap();
+    assert!(explanation.contains("B"), "{}", explanation);
+    Ok(())
+}
+
+#[test]
+fn serve_errors() -> Result<(), Error> {
+    let mut server = server()?;
+    assert_eq!(server.handle("GET", "/kb/unknown/facts", "").0, 404);
+    assert_eq!(server.handle("GET", "/nothing", "").0, 404);
+    assert_eq!(server.handle("DELETE", "/kb/constants/rules", "").0, 405);
+    assert_eq!(server.handle("POST", "/kb/constants/query", "{").0, 400);
+    let (status, body) = server.handle("POST", "/kb/constants/query", "{}");
+    assert_eq!(status, 400);
+    assert_eq!(
+        body.get("error").as_str(),
+        Some("Serve: no queries provided")
+    );
+    let (status, body) =
+        server.handle("POST", "/kb/constants/query", r#"{"queries": ["unknown"]}"#);
+    assert_eq!(status, 400);
+    assert!(body.get("error").as_str().is_some());
+    Ok(())
+}
+
+#[test]
+fn serve_content_length() {
+    assert_eq!(serve::content_length("42"), Ok(42));
+    assert_eq!(serve::content_length("abc"), Err(400));
+    assert_eq!(serve::content_length("-1"), Err(400));
+    assert_eq!(
+        serve::content_length(&(serve::MAX_BODY + 1).to_string()),
+        Err(413)
+    );
+}
+
+#[test]
+fn serve_head() -> Result<(), Error> {
+    let head = |text: String| serve::read_head(&mut Cursor::new(text.into_bytes()));
+    assert_eq!(
+        head("POST /kb HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}".to_string())?,
+        Ok(("POST".to_string(), "/kb".to_string(), 2))
+    );
+    assert_eq!(
+        head("GET /kb HTTP/1.1\r\nContent-Length: x\r\n\r\n".to_string())?,
+        Err(400)
+    );
+    let long = "x".repeat(serve::MAX_HEAD as usize);
+    assert_eq!(head(format!("GET /{} HTTP/1.1\r\n\r\n", long))?, Err(431));
+    assert_eq!(
+        head(format!("GET /kb HTTP/1.1\r\nX: {}\r\n\r\n", long))?,
+        Err(431)
+    );
+    Ok(())
+}
+
+#[test]
+fn serve_rejects_weighted_modes() {
+    for option in ["--certainty", "--fuzzy", "--probability", "--samples=10"] {
+        let mut options = Options::new();
+        options.load(option).unwrap();
+        let error = Server::load(&["testfiles/complete/constants"], options)
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("Serve: --"), "{}", error);
+    }
+}
+
+#[test]
+fn serve_assert_and_retract() -> Result<(), Error> {
+    let mut server = server()?;
+    let query = r#"{"queries": ["B"]}"#;
+    let (_, body) = server.handle("POST", "/kb/descriptions/query", query);
+    assert_eq!(states(&body), vec!["B = True"]);
+
+    let (status, body) = server.handle(
+        "POST",
+        "/kb/descriptions/retract",
+        r#"{"facts": ["cough"]}"#,
+    );
+    assert_eq!(status, 200);
+    assert_eq!(body.to_string(), r#"{"invalidated":["B","flu"]}"#);
+    let (_, body) = server.handle("POST", "/kb/descriptions/query", query);
+    assert_eq!(states(&body), vec!["B = False"]);
+
+    let (_, body) = server.handle(
+        "POST",
+        "/kb/descriptions/assert",
+        r#"{"facts": ["cough", "!A"]}"#,
+    );
+    assert_eq!(body.to_string(), r#"{"invalidated":["B","flu"]}"#);
+    let (_, body) = server.handle("POST", "/kb/descriptions/query", query);
+    assert_eq!(states(&body), vec!["B = False"]);
+    let (_, body) = server.handle("POST", "/kb/descriptions/assert", r#"{"facts": ["A"]}"#);
+    assert_eq!(body.to_string(), r#"{"invalidated":["B","flu"]}"#);
+    let (_, body) = server.handle("POST", "/kb/descriptions/explain", query);
+    assert_eq!(states(&body), vec!["B = True"]);
+    let explanation = body.get("explanation").as_str().unwrap();
+    assert!(explanation.contains("flu => B"), "{}", explanation);
+    Ok(())
+}
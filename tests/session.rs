@@ -0,0 +1,82 @@
+use lib::facts::{Facts, State};
+use lib::options::Options;
+use lib::rules::Rules;
+use lib::session::Session;
+
+use std::io::Error;
+
+fn rules<'a>(facts: &'a Facts, lines: &[&str]) -> Result<Rules<'a>, Error> {
+    let mut rules = Rules::new();
+    for line in lines {
+        rules.set_rule(facts, line, &Options::new())?;
+    }
+    rules.as_reverse_polish_notation()?;
+    Ok(rules)
+}
+
+fn solve(session: &Session, facts: &Facts, rules: &Rules, names: &[&str]) -> Vec<State> {
+    let queries = names.iter().map(|name| facts.get(name).unwrap()).collect();
+    session
+        .solve(queries, rules, &Options::new())
+        .unwrap()
+        .iter()
+        .map(|fact| fact.solved_state())
+        .collect()
+}
+
+#[test]
+fn session_invalidates_dependents_only() -> Result<(), Error> {
+    let facts = Facts::new();
+    let rules = rules(&facts, &["A + B => C", "C => D", "E => F"])?;
+    for name in ["A", "B"].iter() {
+        facts.get(name).unwrap().state.set(true);
+        facts.get(name).unwrap().determined.set(true);
+    }
+    let mut session = Session::new(&facts, &rules);
+    assert!(session.is_asserted(facts.get("A").unwrap()));
+    assert!(!session.is_asserted(facts.get("C").unwrap()));
+    assert_eq!(
+        solve(&session, &facts, &rules, &["D", "F"]),
+        vec![State::True, State::False]
+    );
+
+    let invalidated = session.retract(facts.get("B").unwrap());
+    assert_eq!(invalidated, vec!["C", "D"]);
+    // F does not depend on B, it stays solved.
+    assert!(facts.get("F").unwrap().determined.get());
+    assert!(!facts.get("D").unwrap().determined.get());
+    assert_eq!(solve(&session, &facts, &rules, &["D"]), vec![State::False]);
+
+    let invalidated = session.assert(facts.get("B").unwrap(), true);
+    assert_eq!(invalidated, vec!["C", "D"]);
+    assert_eq!(solve(&session, &facts, &rules, &["D"]), vec![State::True]);
+
+    let invalidated = session.assert(facts.get("E").unwrap(), true);
+    assert_eq!(invalidated, vec!["F"]);
+    assert!(facts.get("D").unwrap().determined.get());
+    assert_eq!(solve(&session, &facts, &rules, &["F"]), vec![State::True]);
+    Ok(())
+}
+
+#[test]
+fn session_asserted_facts() -> Result<(), Error> {
+    let facts = Facts::new();
+    let rules = rules(&facts, &["A => B"])?;
+    let mut session = Session::new(&facts, &rules);
+    session.assert(facts.get("A").unwrap(), true);
+    assert_eq!(solve(&session, &facts, &rules, &["B"]), vec![State::True]);
+    let asserted = session.asserted_facts();
+    let saved = |name: &str| asserted.iter().find(|fact| fact.name == name).unwrap();
+    assert!(saved("A").determined.get());
+    assert!(!saved("B").determined.get());
+
+    // Asserting a derived fact overrides its rules until retracted.
+    assert_eq!(
+        session.assert(facts.get("B").unwrap(), false),
+        Vec::<String>::new()
+    );
+    assert_eq!(solve(&session, &facts, &rules, &["B"]), vec![State::False]);
+    session.retract(facts.get("B").unwrap());
+    assert_eq!(solve(&session, &facts, &rules, &["B"]), vec![State::True]);
+    Ok(())
+}
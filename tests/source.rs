@@ -0,0 +1,89 @@
+use lib::lexer;
+use lib::source::{Line, Source};
+
+use std::io::Error;
+use std::path::Path;
+
+#[test]
+fn source_single_file() -> Result<(), Error> {
+    let source = Source::load(Path::new("testfiles/complete/basic_xor"))?;
+    assert_eq!(source.files.len(), 1);
+    assert_eq!(source.lines[0].location(), "basic_xor:1");
+    assert_eq!(source.lines[0].text, "A ^ B => C");
+    Ok(())
+}
+#[test]
+fn source_include_dedup() -> Result<(), Error> {
+    let source = Source::load(Path::new("testfiles/include/main"))?;
+    assert_eq!(source.files.len(), 3);
+    let rules: Vec<String> = source
+        .lines
+        .iter()
+        .filter(|line| line.text.contains("=>"))
+        .map(|line| format!("{} {}", line.location(), line.text))
+        .collect();
+    assert_eq!(
+        rules,
+        vec![
+            "modules/common:2 A | B => C",
+            "modules/fever:2 C + D => E",
+            "main:4 E => F",
+        ]
+    );
+    Ok(())
+}
+#[test]
+fn source_include_cycle() {
+    match Source::load(Path::new("testfiles/include/modules/cycle_a")) {
+        Err(error) => assert_eq!(error.to_string(), "cycle_b:1: Include: cycle (cycle_a)"),
+        Ok(_) => panic!("include cycle should be rejected"),
+    }
+}
+#[test]
+fn source_multiline_rules() -> Result<(), Error> {
+    let source = Source::load(Path::new("testfiles/complete/multiline"))?;
+    let rules: Vec<&Line> = source
+        .lines
+        .iter()
+        .filter(|line| line.text.contains("=>"))
+        .collect();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].location(), "multiline:2");
+    assert_eq!(rules[0].text, "(A + B \n  | C) =>\n    D");
+    assert_eq!(
+        rules[0].position(rules[0].text.find('D').unwrap()),
+        "multiline:4:5"
+    );
+    assert_eq!(rules[1].location(), "multiline:6");
+    assert_eq!(
+        rules[1].position(rules[1].text.find("=>").unwrap()),
+        "multiline:9:5"
+    );
+    Ok(())
+}
+#[test]
+fn continuation_lines() {
+    assert!(lexer::continues("A + # comment"));
+    assert!(lexer::continues("(A | (B"));
+    assert!(lexer::continues("A =>"));
+    assert!(!lexer::continues("(A | B) => C # (open"));
+    assert!(lexer::continuation("  => C"));
+    assert!(!lexer::continuation("=A"));
+    assert!(!lexer::continuation("   "));
+}
+#[test]
+fn logical_lines() {
+    let lines = [
+        "(A + # first",
+        "  B) => # second",
+        "  C",
+        "module m",
+        "D =>",
+        "=A",
+    ];
+    let ranges = lexer::logical_lines(&lines);
+    assert_eq!(ranges, vec![0..3, 3..4, 4..5, 5..6]);
+    let (text, comments) = lexer::join(&lines[ranges[0].clone()]);
+    assert_eq!(text, "(A + \n  B) => \n  C");
+    assert_eq!(comments, vec!["# first", "# second"]);
+}
@@ -0,0 +1,147 @@
+use lib::syntax::{self, tokens::TokenKind, NodeKind, SyntaxElement, SyntaxNode};
+
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+
+fn files(dir: &Path, found: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files(&path, found)?;
+        } else if !path.to_string_lossy().ends_with(".py")
+            && !path.starts_with("testfiles/templates")
+        {
+            found.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn syntax_round_trip() -> Result<(), Error> {
+    let mut found = Vec::new();
+    files(Path::new("testfiles"), &mut found)?;
+    for name in found {
+        // Files that are not text are rejected before any parsing.
+        let text = match String::from_utf8(fs::read(&name)?) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        let tree = syntax::parse(&text);
+        assert_eq!(tree.root.to_string(), text, "{}", name);
+        let mut at = 0;
+        for token in tree.root.tokens() {
+            assert_eq!(token.span.start, at, "{}", name);
+            assert_eq!(token.span.text(&text), token.text, "{}", name);
+            at = token.span.end;
+        }
+        if !["error", "invalide", "bad", ".ref"]
+            .iter()
+            .any(|bad| name.contains(bad))
+        {
+            assert!(tree.errors.is_empty(), "{}: {:?}", name, tree.errors);
+        }
+    }
+    Ok(())
+}
+fn node(element: &SyntaxElement) -> &SyntaxNode {
+    match element {
+        SyntaxElement::Node(node) => node,
+        SyntaxElement::Token(token) => panic!("token {:?}", token),
+    }
+}
+#[test]
+fn syntax_rule_structure() {
+    let text = "[flu] A + B | !(C => D) => E # comment\n=AB\n?(A | B) + atleast(1, C, D)\n";
+    let tree = syntax::parse(text);
+    assert!(tree.errors.is_empty());
+    let lines: Vec<&SyntaxNode> = tree.root.nodes().collect();
+    let kinds: Vec<NodeKind> = lines.iter().map(|line| line.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![NodeKind::Rule, NodeKind::InitialFacts, NodeKind::Queries]
+    );
+    let rule: Vec<&SyntaxNode> = lines[0].nodes().collect();
+    assert_eq!(rule[0].kind, NodeKind::Label);
+    assert_eq!(rule[0].span.text(text), "[flu]");
+    // A + B | ... reads (A + B) | ...
+    let or = rule[1];
+    assert_eq!(or.span.text(text), "A + B | !(C => D)");
+    let operands: Vec<&SyntaxNode> = or.nodes().collect();
+    assert_eq!(operands[0].span.text(text), "A + B");
+    assert_eq!(operands[1].kind, NodeKind::Unary);
+    let comment = lines[0].tokens().into_iter().last().unwrap();
+    assert_eq!(comment.kind, TokenKind::Newline);
+    let query = node(
+        lines[2]
+            .children
+            .iter()
+            .find(|c| matches!(c, SyntaxElement::Node(_)))
+            .unwrap(),
+    );
+    assert_eq!(query.kind, NodeKind::Binary);
+    let cardinality: Vec<&SyntaxNode> = query.nodes().collect();
+    assert_eq!(cardinality[1].kind, NodeKind::Cardinality);
+    assert!(cardinality[1]
+        .tokens()
+        .iter()
+        .any(|token| token.kind == TokenKind::Number && token.text == "1"));
+}
+#[test]
+fn syntax_multiline_rule() {
+    let text = "(A + B # first\n  | C) =>\n  D\n=A\n";
+    let tree = syntax::parse(text);
+    assert!(tree.errors.is_empty());
+    let lines: Vec<&SyntaxNode> = tree.root.nodes().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].span.text(text), "(A + B # first\n  | C) =>\n  D\n");
+}
+#[test]
+fn syntax_certainty() {
+    let text = "A + B => C [0.8]\n=A [-0.5] B\n";
+    let tree = syntax::parse(text);
+    assert!(tree.errors.is_empty(), "{:?}", tree.errors);
+    let lines: Vec<&SyntaxNode> = tree.root.nodes().collect();
+    for (line, factor) in lines.iter().zip(["[0.8]", "[-0.5]"].iter()) {
+        let certainty = line
+            .nodes()
+            .find(|node| node.kind == NodeKind::Certainty)
+            .unwrap();
+        assert_eq!(certainty.span.text(text), *factor);
+    }
+}
+#[test]
+fn syntax_comparison() {
+    let text = "t >= 38 + !(age < 2) => fever\n=t = 38.5 age = 30\n";
+    let tree = syntax::parse(text);
+    assert!(tree.errors.is_empty(), "{:?}", tree.errors);
+    let rule = tree.root.nodes().next().unwrap();
+    let and = rule.nodes().next().unwrap();
+    let operands: Vec<&SyntaxNode> = and.nodes().collect();
+    assert_eq!(operands[0].kind, NodeKind::Comparison);
+    assert_eq!(operands[0].span.text(text), "t >= 38");
+    assert_eq!(operands[1].kind, NodeKind::Unary);
+}
+#[test]
+fn syntax_errors() {
+    let text = "A + $ => B\nA + => B\nfever: high\n(A | B => C\n";
+    let tree = syntax::parse(text);
+    assert_eq!(tree.root.to_string(), text);
+    let errors: Vec<(&str, &str)> = tree
+        .errors
+        .iter()
+        .map(|e| (e.span.text(text), e.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ("$", "Syntax: unexpected token"),
+            ("=>", "Syntax: missing operand"),
+            ("", "Syntax: unclosed bracket"),
+            ("", "Syntax: expected => or <=>"),
+        ]
+    );
+    let lines: Vec<&SyntaxNode> = tree.root.nodes().collect();
+    assert_eq!(lines[2].kind, NodeKind::Declaration);
+}
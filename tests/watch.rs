@@ -0,0 +1,53 @@
+use lib::options::Options;
+use lib::watch::{self, Watcher};
+
+use std::fs;
+use std::io::Error;
+
+#[test]
+fn watch_included_files() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join(format!("expert_system_watch_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let main = dir.join("main");
+    let common = dir.join("common");
+    fs::write(&common, "A => B\n")?;
+    fs::write(&main, "include \"common\"\n=A\n?B\n")?;
+
+    let mut watcher = Watcher::new(&main);
+    assert_eq!(watcher.files_watched(), 2);
+    assert!(!watcher.changed());
+    fs::write(&common, "A => !B\n")?;
+    assert!(watcher.changed());
+    assert!(!watcher.changed());
+
+    // A broken include still watches the files seen before.
+    fs::write(&main, "include \"missing\"\n=A\n?B\n")?;
+    assert!(watcher.changed());
+    fs::write(&common, "A => B\n")?;
+    assert!(watcher.changed());
+    Ok(())
+}
+
+#[test]
+fn watch_report() {
+    let first = watch::report(None, "B = True\n", 1);
+    assert_eq!(first, "=== run 1 ===\nB = True\n");
+    let same = watch::report(Some("B = True\n"), "B = True\n", 2);
+    assert_eq!(
+        same,
+        "=== run 2 ===\nB = True\nNo change since the previous run.\n"
+    );
+    let changed = watch::report(Some("B = True\n"), "B = False\n", 3);
+    assert!(changed.starts_with("=== run 3 ===\nB = False\nChanges since the previous run:\n"));
+    assert!(changed.contains("-B = True\n+B = False\n"), "{}", changed);
+}
+
+#[test]
+fn watch_option() -> Result<(), Error> {
+    let mut options = Options::new();
+    options.load("--watch")?;
+    assert!(options.watch);
+    assert!(!options.comment);
+    Ok(())
+}